@@ -1,11 +1,19 @@
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
 
 use crate::device::Device;
-use crate::event::BltEvent;
+use crate::event::{BltEvent, FsEvent};
+use crate::library::LIBRARY;
 use crate::menus::{self, LinkedMenu, Menu, MenuFrame, TableMenu};
+use crate::playlist::{self, Playlist, PlaylistEdit};
+use crate::prompt::Prompt;
+use crate::scrobble;
+use crate::search::Search;
+use crate::sleep::SleepTimer;
+use crate::stats::STATS;
 use crate::trace_dbg;
 use crate::{
-    audio_player::AudioPlayer,
+    audio_player::{AudioPlayer, RepeatMode},
     event::{AppEvent, Event, EventHandler},
 };
 use bluer::Address;
@@ -17,9 +25,19 @@ use ratatui::{
     crossterm::event::{KeyCode, KeyEvent, KeyModifiers},
 };
 
+/// How long a status message shows for
+const STATUS_DURATION: Duration = Duration::from_secs(5);
+
 pub struct AppState {
     pub player: AudioPlayer,
     pub devices: HashMap<Address, Device>,
+    pub sleep: Option<SleepTimer>,
+    /// Message about the last action and when it was set, cleared by the next key or after a while
+    pub status: Option<(String, Instant)>,
+    /// Playlists in the music dir, kept current by the watcher
+    pub playlists: Vec<Playlist>,
+    /// Changes since the last tick for menus to refresh from
+    pub fs_events: Vec<FsEvent>,
 }
 
 impl AppState {
@@ -27,18 +45,31 @@ impl AppState {
         Self {
             player: AudioPlayer::new(),
             devices: HashMap::default(),
+            sleep: None,
+            status: None,
+            playlists: Vec::new(),
+            fs_events: Vec::new(),
         }
     }
 
     pub fn cloned_devices(&self) -> Vec<Device> {
         self.devices.clone().into_values().collect()
     }
+
+    /// Tells the user an action succeeded, errors are warnings of the player instead
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = Some((status.into(), Instant::now()));
+    }
 }
 
 /// Application.
 pub struct App {
     pub state: AppState,
     pub menu: LinkedMenu,
+    /// Search overlay taking the keys while open
+    pub search: Option<Search>,
+    /// Text prompt taking the keys while open, over the search
+    pub prompt: Option<Prompt>,
     /// Is the application running?
     pub running: bool,
     /// Event handler.
@@ -48,11 +79,14 @@ pub struct App {
 impl App {
     /// Constructs a new instance of [`App`].
     pub async fn new() -> Self {
+        LIBRARY.scan();
         Self {
             state: AppState::new(),
             running: true,
             events: EventHandler::new(),
             menu: menus::make_test_menu(),
+            search: None,
+            prompt: None,
         }
     }
 
@@ -84,8 +118,18 @@ impl App {
                     AppEvent::Push(func) => {
                         self.menu.push(func());
                     }
-                    AppEvent::Play(playlist) => {
-                        self.state.player.queue_playlist(playlist);
+                    AppEvent::Info(func) => {
+                        self.menu.push(func());
+                    }
+                    AppEvent::PlayNow(tracks) => {
+                        let result = self.state.player.play_now(tracks);
+                        self.warn(result);
+                    }
+                    AppEvent::PlayNext(tracks) => {
+                        self.state.player.play_next(tracks);
+                    }
+                    AppEvent::Enqueue(tracks) => {
+                        self.state.player.enqueue(tracks);
                     }
                     AppEvent::Resume => {
                         self.state.player.resume();
@@ -93,6 +137,221 @@ impl App {
                     AppEvent::Pause => {
                         self.state.player.pause();
                     }
+                    AppEvent::Previous => {
+                        let result = self.state.player.previous();
+                        self.warn(result);
+                    }
+                    AppEvent::Restart => {
+                        let result = self.state.player.restart();
+                        self.warn(result);
+                    }
+                    AppEvent::QueueJump(index) => {
+                        let result = self.state.player.jump_queued(index);
+                        self.warn(result);
+                        self.menu.pop();
+                    }
+                    AppEvent::QueueRemove(index) => {
+                        self.state.player.remove_queued(index);
+                        self.menu.pop();
+                    }
+                    AppEvent::QueueMoveUp(index) => {
+                        self.state.player.move_queued_up(index);
+                        self.menu.pop();
+                    }
+                    AppEvent::QueueMoveDown(index) => {
+                        self.state.player.move_queued_down(index);
+                        self.menu.pop();
+                    }
+                    AppEvent::ClearQueue => {
+                        self.state.player.clear_queue();
+                    }
+                    AppEvent::ToggleShuffle => {
+                        self.state.player.toggle_shuffle();
+                    }
+                    AppEvent::CycleRepeat => {
+                        self.state.player.cycle_repeat();
+                    }
+                    AppEvent::VolumeUp => {
+                        self.state.player.volume_up();
+                    }
+                    AppEvent::VolumeDown => {
+                        self.state.player.volume_down();
+                    }
+                    AppEvent::ToggleMute => {
+                        self.state.player.toggle_mute();
+                    }
+                    AppEvent::Seek(pos) => {
+                        let result = self.state.player.seek(pos);
+                        self.warn(result);
+                    }
+                    AppEvent::SeekForward(by) => {
+                        let result = self.state.player.seek_forward(by);
+                        self.warn(result);
+                    }
+                    AppEvent::SeekBackward(by) => {
+                        let result = self.state.player.seek_backward(by);
+                        self.warn(result);
+                    }
+                    AppEvent::SpeedUp => {
+                        self.state.player.speed_up();
+                    }
+                    AppEvent::SpeedDown => {
+                        self.state.player.speed_down();
+                    }
+                    AppEvent::ResetSpeed => {
+                        self.state.player.reset_speed();
+                    }
+                    AppEvent::TogglePreservePitch => {
+                        self.state.player.toggle_preserve_pitch();
+                    }
+                    AppEvent::NextChapter => {
+                        let result = self.state.player.next_chapter();
+                        self.warn(result);
+                    }
+                    AppEvent::PreviousChapter => {
+                        let result = self.state.player.previous_chapter();
+                        self.warn(result);
+                    }
+                    AppEvent::ChapterJump(track, pos) => {
+                        let result = self.state.player.play_from(track, pos);
+                        self.warn(result);
+                    }
+                    AppEvent::AddBookmark => {
+                        if let Some(track) = self.state.player.get_current().cloned() {
+                            let position = self.state.player.get_pos();
+                            let name = self.state.player.bookmark_name(&track.path);
+                            self.prompt = Some(Prompt::new("Bookmark", name, move |name| {
+                                AppEvent::SaveBookmark(track.clone(), position, name)
+                            }));
+                        }
+                    }
+                    AppEvent::SaveBookmark(track, position, name) => {
+                        self.state.player.add_bookmark(&track, position, name);
+                    }
+                    AppEvent::BookmarkJump(track, pos) => {
+                        let result = self.state.player.play_from(track, pos);
+                        self.warn(result);
+                        self.menu.pop();
+                    }
+                    AppEvent::BookmarkRemove(track, index) => {
+                        self.state.player.remove_bookmark(&track, index);
+                        self.menu.pop();
+                    }
+                    AppEvent::MarkLoop => {
+                        self.state.player.mark_loop();
+                    }
+                    AppEvent::ClearLoop => {
+                        self.state.player.clear_loop();
+                    }
+                    AppEvent::CycleLoopSlowdown => {
+                        self.state.player.cycle_loop_slowdown();
+                    }
+                    AppEvent::RescanLibrary => {
+                        LIBRARY.scan();
+                    }
+                    AppEvent::NewPlaylist(tracks) => {
+                        self.prompt = Some(Prompt::new("Playlist Title", "", move |title| {
+                            AppEvent::CreatePlaylist(title, tracks.clone())
+                        }));
+                    }
+                    AppEvent::CreatePlaylist(title, tracks) => {
+                        match playlist::create(&title, &tracks) {
+                            Ok(playlist) => {
+                                self.state
+                                    .fs_events
+                                    .push(FsEvent::PlaylistChanged(playlist));
+                                // Created from the playlist picker
+                                if !tracks.is_empty() {
+                                    self.menu.pop();
+                                }
+                            }
+                            Err(err) => self
+                                .state
+                                .player
+                                .set_warning(format!("Could not create playlist: {err}")),
+                        }
+                    }
+                    AppEvent::RenamePlaylist(path, title) => {
+                        self.prompt = Some(Prompt::new("Rename Playlist", title, move |title| {
+                            AppEvent::EditPlaylist(path.clone(), PlaylistEdit::Rename(title))
+                        }));
+                    }
+                    AppEvent::AddToPlaylist(tracks) => {
+                        self.menu.push(playlist::playlist_picker(tracks));
+                    }
+                    AppEvent::EditPlaylist(path, edit) => {
+                        // Renames come from a prompt, the other edits from a menu of their own
+                        let pop = !matches!(edit, PlaylistEdit::Rename(_));
+                        match playlist::edit(&path, edit) {
+                            Ok(playlist) => self
+                                .state
+                                .fs_events
+                                .push(FsEvent::PlaylistChanged(playlist)),
+                            Err(err) => self
+                                .state
+                                .player
+                                .set_warning(format!("Could not edit playlist: {err}")),
+                        }
+                        if pop {
+                            self.menu.pop();
+                        }
+                    }
+                    AppEvent::DeletePlaylist(path) => {
+                        let title = format!(
+                            "Delete {}?",
+                            path.file_name().unwrap_or_default().to_string_lossy()
+                        );
+                        self.menu.push(LinkedMenu::new(Box::new(
+                            TableMenu::new(
+                                vec![AppEvent::ConfirmDeletePlaylist(path), AppEvent::Pop],
+                                [Constraint::Fill(100)],
+                            )
+                            .with_header(Row::new([Cell::new(title)])),
+                        )));
+                    }
+                    AppEvent::ConfirmDeletePlaylist(path) => {
+                        match std::fs::remove_file(&path) {
+                            Ok(()) => self.state.fs_events.push(FsEvent::PlaylistRemoved(path)),
+                            Err(err) => self
+                                .state
+                                .player
+                                .set_warning(format!("Could not delete playlist: {err}")),
+                        }
+                        self.menu.pop();
+                    }
+                    AppEvent::ExportPlaylist(path) => match playlist::export_m3u(&path) {
+                        Ok(export) => self
+                            .state
+                            .set_status(format!("Exported to {}", export.display())),
+                        Err(err) => self
+                            .state
+                            .player
+                            .set_warning(format!("Could not export playlist: {err}")),
+                    },
+                    AppEvent::SetRating(path, rating) => STATS.set_rating(&path, rating),
+                    AppEvent::ToggleFavorite(path) => STATS.toggle_favorite(&path),
+                    AppEvent::ResetStats(path) => STATS.reset(&path),
+                    AppEvent::ExportListens => match scrobble::export_listenbrainz() {
+                        Ok(export) => self
+                            .state
+                            .set_status(format!("Exported to {}", export.display())),
+                        Err(err) => self
+                            .state
+                            .player
+                            .set_warning(format!("Could not export listens: {err}")),
+                    },
+                    AppEvent::SetSleepTimer(preset) => {
+                        self.state.player.set_fade(1.0);
+                        self.state.sleep = SleepTimer::new(preset, &self.state.player);
+                        self.menu.pop();
+                    }
+                    AppEvent::CancelSleepTimer => {
+                        self.state.player.set_fade(1.0);
+                        self.state.sleep = None;
+                    }
+                    AppEvent::SetEqualizer(gains) => {
+                        self.state.player.equalizer().set_gains(gains);
+                    }
                     AppEvent::Connect(device) => {
                         tokio::spawn(async move {
                             let _ = device.pair().await;
@@ -118,6 +377,7 @@ impl App {
                         trace_dbg!("Debuged");
                     }
                 },
+                Event::Fs(fs_event) => self.state.fs_events.push(fs_event),
                 Event::Blt(device_event) => match device_event {
                     BltEvent::Add(dev) => {
                         self.add_device(dev);
@@ -126,11 +386,19 @@ impl App {
                 },
             }
         }
+        self.state.player.save_position();
         Ok(())
     }
 
     /// Handles the key events and updates the state of [`App`].
     pub fn handle_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
+        self.state.status = None;
+        if self.prompt.is_some() {
+            return self.handle_prompt_key_events(key_event);
+        }
+        if self.search.is_some() {
+            return self.handle_search_key_events(key_event);
+        }
         match key_event.code {
             KeyCode::Esc | KeyCode::Char('q') => self.events.send(AppEvent::Quit),
             KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
@@ -139,6 +407,101 @@ impl App {
             KeyCode::Up => self.events.send(AppEvent::Up),
             KeyCode::Down => self.events.send(AppEvent::Down),
             KeyCode::Enter => self.events.send(AppEvent::Enter),
+            KeyCode::Left if key_event.modifiers == KeyModifiers::SHIFT => self
+                .events
+                .send(AppEvent::SeekBackward(Duration::from_secs(30))),
+            KeyCode::Right if key_event.modifiers == KeyModifiers::SHIFT => self
+                .events
+                .send(AppEvent::SeekForward(Duration::from_secs(30))),
+            // Menus like the equalizer use Left and Right before they seek
+            KeyCode::Left => {
+                if !self.menu.left() {
+                    self.events
+                        .send(AppEvent::SeekBackward(Duration::from_secs(5)))
+                }
+            }
+            KeyCode::Right => {
+                if !self.menu.right() {
+                    self.events
+                        .send(AppEvent::SeekForward(Duration::from_secs(5)))
+                }
+            }
+            KeyCode::Char('r') => self.events.send(AppEvent::Restart),
+            KeyCode::Char('p') => self.events.send(AppEvent::Previous),
+            KeyCode::Char('s') => self.events.send(AppEvent::ToggleShuffle),
+            KeyCode::Char('R') => self.events.send(AppEvent::CycleRepeat),
+            KeyCode::Char('+' | '=') => self.events.send(AppEvent::VolumeUp),
+            KeyCode::Char('-') => self.events.send(AppEvent::VolumeDown),
+            KeyCode::Char('m') => self.events.send(AppEvent::ToggleMute),
+            KeyCode::Char(']') => self.events.send(AppEvent::SpeedUp),
+            KeyCode::Char('[') => self.events.send(AppEvent::SpeedDown),
+            KeyCode::Char('\\') => self.events.send(AppEvent::ResetSpeed),
+            KeyCode::Char('P') => self.events.send(AppEvent::TogglePreservePitch),
+            KeyCode::Char('b') => self.events.send(AppEvent::AddBookmark),
+            KeyCode::Char('.') => self.events.send(AppEvent::NextChapter),
+            KeyCode::Char(',') => self.events.send(AppEvent::PreviousChapter),
+            KeyCode::Char('l') => self.events.send(AppEvent::MarkLoop),
+            KeyCode::Char('L') => self.events.send(AppEvent::ClearLoop),
+            KeyCode::Char(';') => self.events.send(AppEvent::CycleLoopSlowdown),
+            KeyCode::Char('/') => self.search = Some(Search::new(&self.state)),
+            KeyCode::Char('o') => {
+                self.menu.sort();
+            }
+            // Scrub to a tenth of the track
+            KeyCode::Char(digit @ '0'..='9') => {
+                if let Some(current) = self.state.player.get_current() {
+                    let tenths = digit.to_digit(10).unwrap_or_default();
+                    self.events.send(AppEvent::Seek(
+                        current.total_duration.mul_f64(tenths as f64 / 10.0),
+                    ))
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Handles the key events while prompting, typed text goes to the prompt
+    fn handle_prompt_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return Ok(());
+        };
+        match key_event.code {
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.events.send(AppEvent::Quit)
+            }
+            KeyCode::Backspace => prompt.pop(),
+            KeyCode::Char(c) => prompt.push(c),
+            KeyCode::Enter => {
+                self.events.send(prompt.submit());
+                self.prompt = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Handles the key events while searching, typed text goes to the query
+    fn handle_search_key_events(&mut self, key_event: KeyEvent) -> color_eyre::Result<()> {
+        let Some(search) = self.search.as_mut() else {
+            return Ok(());
+        };
+        match key_event.code {
+            KeyCode::Esc => self.search = None,
+            KeyCode::Char('c' | 'C') if key_event.modifiers == KeyModifiers::CONTROL => {
+                self.events.send(AppEvent::Quit)
+            }
+            KeyCode::Up => search.up(),
+            KeyCode::Down => search.down(),
+            KeyCode::Backspace => search.pop(),
+            KeyCode::Char(c) => search.push(c),
+            KeyCode::Enter => {
+                if let Some(event) = search.enter()? {
+                    self.events.send(event);
+                }
+                self.search = None;
+            }
             _ => {}
         }
         Ok(())
@@ -149,8 +512,41 @@ impl App {
     /// The tick event is where you can update the state of your application with any logic that
     /// needs to be updated at a fixed frame rate. E.g. polling a server, updating an animation.
     pub fn tick(&mut self) -> color_eyre::Result<()> {
-        self.state.player.tick()?;
-        self.menu.tick(&self.state)
+        let result = self.state.player.tick();
+        self.warn(result);
+        if let Some(sleep) = self.state.sleep.as_ref()
+            && sleep.tick(&mut self.state.player)
+        {
+            self.state.sleep = None;
+        }
+        if self
+            .state
+            .status
+            .as_ref()
+            .is_some_and(|(_, set)| set.elapsed() >= STATUS_DURATION)
+        {
+            self.state.status = None;
+        }
+        if LIBRARY.take_scanned() {
+            self.state.fs_events.push(FsEvent::LibraryScanned);
+        }
+        if STATS.take_changed() {
+            self.state.fs_events.push(FsEvent::StatsChanged);
+        }
+        let mut playlists = std::mem::take(&mut self.state.playlists);
+        playlist::refresh_playlists(&mut playlists, &self.state)?;
+        self.state.playlists = playlists;
+        self.menu.tick(&self.state)?;
+        self.state.fs_events.clear();
+        Ok(())
+    }
+
+    /// Shows a failed player action as a warning instead of ending the app
+    fn warn(&mut self, result: color_eyre::Result<()>) {
+        if let Err(err) = result {
+            trace_dbg!(&err);
+            self.state.player.set_warning(err.to_string());
+        }
     }
 
     /// Set running to false to quit the application.
@@ -197,6 +593,27 @@ pub fn quick_menu() -> Box<dyn Menu> {
                     }
                 }
 
+                if let Some(current) = app_state.player.get_current() {
+                    if !current.chapters.is_empty() {
+                        items.push(AppEvent::NextChapter);
+                        items.push(AppEvent::PreviousChapter);
+                    }
+                    items.push(AppEvent::Previous);
+                    items.push(AppEvent::ToggleShuffle);
+                    items.push(AppEvent::CycleRepeat);
+                    items.push(AppEvent::AddBookmark);
+                    items.push(AppEvent::AddToPlaylist(vec![current.clone()]));
+                    items.push(AppEvent::MarkLoop);
+                    if app_state.player.get_loop().is_some() {
+                        items.push(AppEvent::CycleLoopSlowdown);
+                        items.push(AppEvent::ClearLoop);
+                    }
+                }
+
+                if app_state.sleep.is_some() {
+                    items.push(AppEvent::CancelSleepTimer);
+                }
+
                 items.push(AppEvent::Pop);
                 Ok(())
             }),
@@ -208,6 +625,8 @@ pub struct AudioWidgetMenu {
     progress: f64,
     title: String,
     progress_label: String,
+    /// Where the A and B loop markers are as ratios of the track
+    loop_markers: [Option<f64>; 2],
     render: bool,
 }
 
@@ -227,15 +646,27 @@ impl Menu for AudioWidgetMenu {
         buf: &mut ratatui::prelude::Buffer,
         focused: bool,
     ) {
+        let area = Layout::vertical([Constraint::Fill(100), Constraint::Length(3)]).split(area)[1];
         Gauge::default()
             .ratio(self.progress)
             .label(self.progress_label.clone())
             .gauge_style(Style::new().white().on_black())
             .block(Block::bordered().title_top(self.title.clone()))
-            .render(
-                Layout::vertical([Constraint::Fill(100), Constraint::Length(3)]).split(area)[1],
-                buf,
-            );
+            .render(area, buf);
+
+        // Loop markers drawn over the bar inside the border
+        let width = area.width.saturating_sub(2);
+        for (marker, symbol) in self.loop_markers.iter().zip(["A", "B"]) {
+            if let Some(ratio) = marker
+                && width > 0
+            {
+                let x = area.x + 1 + (ratio * (width - 1) as f64).round() as u16;
+                if let Some(cell) = buf.cell_mut((x, area.y + 1)) {
+                    cell.set_symbol(symbol)
+                        .set_style(Style::new().black().on_yellow());
+                }
+            }
+        }
     }
     fn constraint(&self) -> Constraint {
         Constraint::Fill(100)
@@ -243,7 +674,54 @@ impl Menu for AudioWidgetMenu {
     fn tick(&mut self, app_state: &AppState) -> color_eyre::Result<()> {
         match app_state.player.get_current() {
             Some(track) => {
-                self.title = track.title.clone();
+                let speed = app_state.player.get_speed();
+                self.title = format!(
+                    "{}{}{}{}{}{}",
+                    track.title,
+                    match app_state.player.get_chapter() {
+                        Some(chapter) => format!(" - {}", chapter.title),
+                        None => String::new(),
+                    },
+                    if speed != 1.0 {
+                        format!(
+                            " [{speed}x{}]",
+                            if app_state.player.preserves_pitch() {
+                                ""
+                            } else {
+                                " pitched"
+                            }
+                        )
+                    } else {
+                        String::new()
+                    },
+                    if app_state.player.is_shuffled() {
+                        " [shuffle]"
+                    } else {
+                        ""
+                    },
+                    match app_state.player.get_repeat() {
+                        RepeatMode::Off => String::new(),
+                        repeat => format!(" [repeat {repeat}]"),
+                    },
+                    match app_state.player.get_loop() {
+                        Some(ab_loop) if ab_loop.slowdown != 1.0 => {
+                            format!(" [loop {}x]", ab_loop.slowdown)
+                        }
+                        Some(_) => String::from(" [loop]"),
+                        None => String::new(),
+                    }
+                );
+                let ratio = |pos: Duration| {
+                    if track.total_duration.is_zero() {
+                        0.0
+                    } else {
+                        pos.div_duration_f64(track.total_duration).clamp(0.0, 1.0)
+                    }
+                };
+                self.loop_markers = match app_state.player.get_loop() {
+                    Some(ab_loop) => [Some(ratio(ab_loop.a)), ab_loop.b.map(ratio)],
+                    None => [None, None],
+                };
                 self.progress_label = app_state.player.get_progress_label();
                 self.progress = app_state.player.get_progress();
             }
@@ -1,17 +1,186 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use hhmmss::Hhmmss;
-use rodio::{OutputStream, Sink};
+use ratatui::{
+    layout::Constraint,
+    text::Text,
+    widgets::{Cell, Row},
+};
+use rodio::{Sink, Source, source::SeekError};
+use strum_macros::Display;
 use tracing::{Level, instrument, span, trace};
 
-use crate::track::Track;
+use crate::{
+    CONFIG,
+    app::quick_menu,
+    chapters::Chapter,
+    equalizer::{Equalizer, EqualizerControl},
+    event::AppEvent,
+    logging::get_data_dir,
+    menus::{Item, LinkedMenu, MenuFrame, TableMenu},
+    output::{self, Output},
+    resume::{self, Bookmark, ResumeStore},
+    scrobble::{self, Scrobble},
+    stats::{self, STATS},
+    stretch::{SpeedControl, Stretch},
+    trace_dbg,
+    track::{Track, track_header, track_widths},
+};
+
+/// Position after which going to the previous track restarts the current one instead
+const PREVIOUS_RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+
+const VOLUME_STEP: f32 = 0.05;
+const SPEED_STEP: f32 = 0.1;
+const LOOP_SLOWDOWNS: [f32; 4] = [1.0, 0.9, 0.75, 0.5];
+/// File in the data dir the last volume is saved to
+const VOLUME_FILE: &str = "volume";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum RepeatMode {
+    #[default]
+    Off,
+    One,
+    All,
+}
+
+impl RepeatMode {
+    pub fn cycle(self) -> Self {
+        match self {
+            Self::Off => Self::All,
+            Self::All => Self::One,
+            Self::One => Self::Off,
+        }
+    }
+}
+
+/// Section of the current track played over and over
+#[derive(Debug, Clone, Copy)]
+pub struct AbLoop {
+    pub a: Duration,
+    /// Unset until the end of the loop is marked
+    pub b: Option<Duration>,
+    /// Factor of the speed the loop is played at
+    pub slowdown: f32,
+}
+
+type BoxedSource = Box<dyn Source + Send>;
+
+/// Messages sent to the player from decoding threads and the audio thread
+enum PlayerMessage {
+    /// A track finished decoding for the source with an id
+    Decoded(u64, color_eyre::Result<BoxedSource>),
+    /// The source with an id played to its end
+    Finished(u64),
+}
+
+#[derive(PartialEq, Eq)]
+enum PrefetchState {
+    Decoding,
+    Appended,
+    Failed,
+}
+
+/// Shared with the [`Tracked`] source playing a track
+#[derive(Clone)]
+struct SourceHandle {
+    /// Nanoseconds into the track the source ends early at, `u64::MAX` to play it all
+    end: Arc<AtomicU64>,
+    /// Nanoseconds into the track the source has played to
+    position: Arc<AtomicU64>,
+}
+
+impl Default for SourceHandle {
+    fn default() -> Self {
+        Self {
+            end: Arc::new(AtomicU64::new(u64::MAX)),
+            position: Arc::default(),
+        }
+    }
+}
+
+/// Upcoming track decoded ahead and appended to the sink after the current one
+struct Prefetch {
+    id: u64,
+    track: Track,
+    state: PrefetchState,
+    /// Ends the source without playing it once it is stale
+    cancel: Arc<AtomicBool>,
+    handle: SourceHandle,
+    /// Resume position the source was decoded from
+    start: Duration,
+    /// Overlap with the end of the current track
+    crossfade: Option<Duration>,
+}
+
+/// Track being listened to, ended as a play or a skip when another track starts
+struct Listen {
+    track: Track,
+    started: SystemTime,
+    /// Time spent playing the track, not counting pauses or seeks
+    listened: Duration,
+    /// When the listened time was last brought up to date
+    updated: Instant,
+    /// Has the track played to its end
+    completed: bool,
+}
 
 pub struct AudioPlayer {
-    stream_handle: OutputStream,
+    output: Box<dyn Output>,
+    /// Why the configured output could not be used
+    warning: Option<String>,
     sink: Sink,
     current: Option<Track>,
     queue: Vec<Track>,
     history: Vec<Track>,
+    /// Position the current source was decoded from when resuming or when seeking fell back to a
+    /// re-decode
+    offset: Duration,
+    /// Queue order from before shuffling, restored when shuffle is turned off
+    unshuffled: Option<Vec<Track>>,
+    repeat: RepeatMode,
+    volume: f32,
+    muted: bool,
+    /// Fraction of the volume played at while fading out
+    fade: f32,
+    sender: Sender<PlayerMessage>,
+    receiver: Receiver<PlayerMessage>,
+    /// Last id given to a source
+    last_id: u64,
+    /// Id of the source playing the current track
+    current_id: u64,
+    /// Has the current track's source been appended to the sink
+    current_appended: bool,
+    /// Has the current track played to its end with nothing after it
+    finished: bool,
+    prefetch: Option<Prefetch>,
+    current_handle: SourceHandle,
+    crossfade: Duration,
+    /// Previous track and how long it overlaps the start of the current one
+    outgoing: Option<(Track, Duration)>,
+    equalizer: EqualizerControl,
+    speed: SpeedControl,
+    ab_loop: Option<AbLoop>,
+    resume: ResumeStore,
+    /// Resumable track the current source handle belongs to
+    resume_track: Option<Track>,
+    /// Position to start the next started track at instead of its resume position
+    start_at: Option<Duration>,
+    listen: Option<Listen>,
+    /// Number of listens begun, telling each play of the same track apart
+    listens: u64,
+    /// Where the volume is saved
+    data_dir: PathBuf,
 }
 
 impl Debug for AudioPlayer {
@@ -24,21 +193,70 @@ impl Debug for AudioPlayer {
 }
 
 impl AudioPlayer {
+    /// Plays to the configured output falling back to a silent one
     pub fn new() -> Self {
-        let stream_handle =
-            rodio::OutputStreamBuilder::open_default_stream().expect("open default audio stream");
-        let sink = Sink::connect_new(&stream_handle.mixer());
+        let (output, warning) = output::open(&CONFIG.output);
         Self {
-            stream_handle,
+            warning,
+            crossfade: CONFIG.crossfade_duration(),
+            ..Self::with_output(output, get_data_dir())
+        }
+    }
+
+    /// Plays to an output, keeping the volume, equalizer and resume positions in a data dir
+    pub fn with_output(output: Box<dyn Output>, data_dir: PathBuf) -> Self {
+        let sink = Sink::connect_new(output.mixer());
+        let volume = load_volume(&data_dir);
+        sink.set_volume(volume);
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            output,
+            warning: None,
             sink,
             current: None,
             queue: vec![],
             history: vec![],
+            offset: Duration::ZERO,
+            unshuffled: None,
+            repeat: RepeatMode::default(),
+            volume,
+            muted: false,
+            fade: 1.0,
+            sender,
+            receiver,
+            last_id: 0,
+            current_id: 0,
+            current_appended: false,
+            finished: false,
+            prefetch: None,
+            current_handle: SourceHandle::default(),
+            crossfade: Duration::ZERO,
+            outgoing: None,
+            equalizer: EqualizerControl::load(&data_dir),
+            speed: SpeedControl::default(),
+            ab_loop: None,
+            resume: ResumeStore::load(&data_dir),
+            resume_track: None,
+            start_at: None,
+            listen: None,
+            listens: 0,
+            data_dir,
         }
     }
 
-    pub fn queue_playlist(&mut self, tracks: Vec<Track>) {
-        let span = span!(Level::TRACE, "queue playlist");
+    /// Queues tracks to play after everything already queued
+    pub fn enqueue(&mut self, tracks: Vec<Track>) {
+        let span = span!(Level::TRACE, "enqueue");
+        let guard = span.enter();
+
+        self.queue.splice(0..0, tracks.into_iter().rev());
+
+        drop(guard);
+    }
+
+    /// Queues tracks to play after the current track
+    pub fn play_next(&mut self, tracks: Vec<Track>) {
+        let span = span!(Level::TRACE, "play next");
         let guard = span.enter();
 
         for track in tracks.into_iter().rev() {
@@ -48,25 +266,122 @@ impl AudioPlayer {
         drop(guard);
     }
 
+    /// Plays tracks immediately keeping the rest of the queue after them
+    pub fn play_now(&mut self, tracks: Vec<Track>) -> color_eyre::Result<()> {
+        if tracks.is_empty() {
+            return Ok(());
+        }
+        self.play_next(tracks);
+        if let Some(current) = self.current.take() {
+            self.history.push(current);
+        }
+        self.play()
+    }
+
     pub fn tick(&mut self) -> color_eyre::Result<()> {
+        self.update_listen();
+        while let Ok(message) = self.receiver.try_recv() {
+            match message {
+                PlayerMessage::Decoded(id, source) => self.on_decoded(id, source),
+                PlayerMessage::Finished(id) => self.on_finished(id),
+            }
+        }
+
         if !self.sink.is_paused() {
             match self.current.as_ref() {
-                Some(current) => {
-                    if self.sink.empty() && self.has_next() {
-                        self.history.push(current.clone());
-                        self.play()?;
+                Some(_) => {
+                    // Tracks were queued after the last one finished
+                    if self.finished && self.advance(self.repeat) {
+                        self.start();
                     }
                 }
                 None => {
-                    self.play()?;
+                    if self.has_next() {
+                        self.play()?;
+                    }
                 }
             }
         }
+
+        // Jump back to the start of the loop once its end is played
+        if let Some(AbLoop { a, b: Some(b), .. }) = self.ab_loop
+            && self.get_pos() >= b
+        {
+            self.seek(a)?;
+        }
+
+        self.prefetch();
         Ok(())
     }
 
+    /// Starts listening to the current track, counting the previous one as skipped if it did not
+    /// play to its end
+    ///
+    /// Skipped tracks are still scrobbled when enough of them was listened to
+    fn begin_listen(&mut self) {
+        self.update_listen();
+        if let Some(listen) = self.listen.take()
+            && !listen.completed
+        {
+            STATS.record_skip(&listen.track.path);
+            if scrobble::should_scrobble(listen.track.total_duration, listen.listened) {
+                Self::scrobble(&listen);
+            }
+        }
+        self.listens += 1;
+        self.listen = self.current.clone().map(|track| Listen {
+            track,
+            started: SystemTime::now(),
+            listened: Duration::ZERO,
+            updated: Instant::now(),
+            completed: false,
+        });
+    }
+
+    /// Counts the current track as played once it reached its end
+    fn complete_listen(&mut self) {
+        self.update_listen();
+        if let Some(listen) = self.listen.as_mut()
+            && !listen.completed
+        {
+            listen.completed = true;
+            STATS.record_play(&listen.track.path);
+            if scrobble::should_scrobble(listen.track.total_duration, listen.listened) {
+                Self::scrobble(listen);
+            }
+        }
+    }
+
+    /// Adds the time since the last update to the listened time while the track is playing
+    fn update_listen(&mut self) {
+        let playing = !self.sink.is_paused() && !self.finished;
+        if let Some(listen) = self.listen.as_mut() {
+            let now = Instant::now();
+            if playing && !listen.completed {
+                listen.listened += now.duration_since(listen.updated);
+            }
+            listen.updated = now;
+        }
+    }
+
+    /// Number of the current listen, a new one begins whenever a track starts playing
+    pub fn get_listen(&self) -> u64 {
+        self.listens
+    }
+
+    /// Has a listen ended, by its track playing to the end or by another track starting
+    pub fn listen_ended(&self, listen: u64) -> bool {
+        listen != self.listens || self.listen.as_ref().is_none_or(|listen| listen.completed)
+    }
+
+    fn scrobble(listen: &Listen) {
+        if let Err(err) = scrobble::record(&Scrobble::new(&listen.track, listen.started)) {
+            trace_dbg!(err);
+        }
+    }
+
     pub fn play(&mut self) -> color_eyre::Result<()> {
-        self.next()?;
+        self.next();
         self.resume();
         Ok(())
     }
@@ -79,14 +394,271 @@ impl AudioPlayer {
         !self.queue.is_empty()
     }
 
-    fn next(&mut self) -> color_eyre::Result<()> {
+    fn next(&mut self) {
         self.current = self.queue.pop();
+        self.start();
+    }
+
+    /// Moves the current track to the history and takes the next one following a repeat mode
+    ///
+    /// Returns false when there is nothing to play after the current track
+    fn advance(&mut self, repeat: RepeatMode) -> bool {
+        let Some(current) = self.current.clone() else {
+            self.current = self.queue.pop();
+            return self.current.is_some();
+        };
+        match repeat {
+            RepeatMode::One => true,
+            _ if self.has_next() => {
+                self.history.push(current);
+                self.current = self.queue.pop();
+                true
+            }
+            RepeatMode::All => {
+                self.history.push(current);
+                self.queue = self.history.drain(..).rev().collect();
+                self.current = self.queue.pop();
+                true
+            }
+            RepeatMode::Off => false,
+        }
+    }
+
+    /// Track [`AudioPlayer::advance`] would play next
+    fn upcoming(&self) -> Option<&Track> {
+        match self.repeat {
+            RepeatMode::One => self.current.as_ref(),
+            _ if self.has_next() => self.queue.last(),
+            RepeatMode::All => self.history.first().or(self.current.as_ref()),
+            RepeatMode::Off => None,
+        }
+    }
+
+    /// Clears the sink and decodes the current track in the background
+    fn start(&mut self) {
+        let paused = self.sink.is_paused();
+        self.save_position();
+        self.clear_loop();
+        self.cancel_prefetch();
         self.sink.clear();
-        if let Some(track) = self.current.as_ref() {
+        self.offset = match self.start_at.take() {
+            Some(start) => start,
+            None => self
+                .current
+                .as_ref()
+                .map_or(Duration::ZERO, |current| self.resume_position(current)),
+        };
+        self.current_handle = SourceHandle::default();
+        self.resume_track = self.current.clone().filter(resume::is_resumable);
+        self.current_appended = false;
+        self.finished = false;
+        self.outgoing = None;
+        self.begin_listen();
+        self.current_id = self.next_id();
+        if let Some(track) = self.current.clone() {
             trace!("play_next");
-            self.sink.append(track.decode()?);
+            self.decode(self.current_id, track, self.offset, None);
         }
-        Ok(())
+        if !paused {
+            self.sink.play();
+        }
+    }
+
+    fn next_id(&mut self) -> u64 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Decodes a track from `start` on another thread, mixed with the tail of `outgoing` when
+    /// crossfading
+    fn decode(&self, id: u64, track: Track, start: Duration, outgoing: Option<(Track, Duration)>) {
+        let sender = self.sender.clone();
+        std::thread::spawn(move || {
+            let source = match outgoing {
+                Some((outgoing, duration)) => decode_crossfade(&outgoing, &track, duration),
+                None => track.decode().map(|source| skip_to(source, start)),
+            };
+            let _ = sender.send(PlayerMessage::Decoded(id, source));
+        });
+    }
+
+    /// Appends a source starting at `start` into its track
+    fn append<S>(
+        &self,
+        id: u64,
+        source: S,
+        cancel: Arc<AtomicBool>,
+        start: Duration,
+    ) -> SourceHandle
+    where
+        S: Source + Send + 'static,
+    {
+        let handle = SourceHandle::default();
+        handle
+            .position
+            .store(start.as_nanos() as u64, Ordering::Relaxed);
+        let played =
+            (start.as_secs_f64() * source.sample_rate() as f64) as u64 * source.channels() as u64;
+        self.sink.append(Stretch::new(
+            Tracked {
+                inner: Equalizer::new(source, self.equalizer.clone()),
+                id,
+                cancel,
+                handle: handle.clone(),
+                played,
+                sender: self.sender.clone(),
+                done: false,
+            },
+            self.speed.clone(),
+        ));
+        handle
+    }
+
+    fn on_decoded(&mut self, id: u64, source: color_eyre::Result<BoxedSource>) {
+        let source = match source {
+            Ok(source) => source,
+            Err(err) => {
+                trace_dbg!(&err);
+                // Skip tracks that fail to decode, they were never listened to so are not skips
+                if id == self.current_id
+                    && let Some(current) = self.current.take()
+                {
+                    self.set_warning(format!("Could not play {}: {err}", current.path.display()));
+                    self.listen = None;
+                    self.history.push(current);
+                }
+                if let Some(prefetch) = self.prefetch.as_mut().filter(|elem| elem.id == id) {
+                    prefetch.state = PrefetchState::Failed;
+                }
+                return;
+            }
+        };
+
+        if id == self.current_id && !self.current_appended && self.current.is_some() {
+            self.current_handle = self.append(id, source, Arc::default(), self.offset);
+            self.current_appended = true;
+        } else if let Some(prefetch) = self
+            .prefetch
+            .as_mut()
+            .filter(|elem| elem.id == id && elem.state == PrefetchState::Decoding)
+        {
+            prefetch.state = PrefetchState::Appended;
+            let cancel = prefetch.cancel.clone();
+            let crossfade = prefetch.crossfade;
+            let start = prefetch.start;
+            let handle = self.append(id, source, cancel, start);
+            if let Some(prefetch) = self.prefetch.as_mut() {
+                prefetch.handle = handle;
+            }
+            // Cut the current track short where the prefetched source starts mixing in its tail
+            if let (Some(crossfade), Some(current)) = (crossfade, self.current.as_ref()) {
+                self.current_handle.end.store(
+                    current.total_duration.saturating_sub(crossfade).as_nanos() as u64,
+                    Ordering::Relaxed,
+                );
+            }
+        }
+    }
+
+    fn on_finished(&mut self, id: u64) {
+        if id != self.current_id {
+            return;
+        }
+        self.save_position();
+        self.complete_listen();
+        let previous = self.current.clone();
+        if !self.advance(self.repeat) {
+            self.finished = true;
+            return;
+        }
+
+        // The sink moves on to the prefetched source by itself so only the bookkeeping changes
+        match self.prefetch.take() {
+            Some(prefetch)
+                if prefetch.state != PrefetchState::Failed
+                    && self
+                        .current
+                        .as_ref()
+                        .is_some_and(|current| current.path == prefetch.track.path) =>
+            {
+                self.current_id = prefetch.id;
+                self.current_appended = prefetch.state == PrefetchState::Appended;
+                self.current_handle = prefetch.handle;
+                self.offset = prefetch.start;
+                self.resume_track = self.current.clone().filter(resume::is_resumable);
+                self.outgoing = previous.zip(prefetch.crossfade);
+                self.clear_loop();
+                self.begin_listen();
+            }
+            prefetch => {
+                self.prefetch = prefetch;
+                self.start();
+            }
+        }
+    }
+
+    /// Decodes the upcoming track replacing a stale prefetch
+    fn prefetch(&mut self) {
+        if !self.current_appended || self.finished {
+            return;
+        }
+        let stale = match (self.prefetch.as_ref(), self.upcoming()) {
+            (Some(prefetch), Some(upcoming)) => prefetch.track.path != upcoming.path,
+            (None, None) => false,
+            _ => true,
+        };
+        if stale {
+            self.cancel_prefetch();
+            if let Some(track) = self.upcoming().cloned() {
+                let id = self.next_id();
+                let start = match self.current.as_ref() {
+                    Some(current) if current.path == track.path => Duration::ZERO,
+                    _ => self.resume_position(&track),
+                };
+                // Resumed tracks start mid way so there is no start to fade in
+                let crossfade = self
+                    .current
+                    .as_ref()
+                    .filter(|_| start.is_zero())
+                    .and_then(|current| self.crossfade_between(current, &track));
+                self.decode(
+                    id,
+                    track.clone(),
+                    start,
+                    self.current.clone().zip(crossfade),
+                );
+                self.prefetch = Some(Prefetch {
+                    id,
+                    track,
+                    state: PrefetchState::Decoding,
+                    cancel: Arc::default(),
+                    handle: SourceHandle::default(),
+                    start,
+                    crossfade,
+                });
+            }
+        }
+    }
+
+    fn cancel_prefetch(&mut self) {
+        if let Some(prefetch) = self.prefetch.take() {
+            prefetch.cancel.store(true, Ordering::Relaxed);
+            // Let the current track play to its end again
+            if prefetch.state == PrefetchState::Appended && prefetch.crossfade.is_some() {
+                self.current_handle.end.store(u64::MAX, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Overlap between two tracks, skipped for consecutive tracks of the same album
+    fn crossfade_between(&self, outgoing: &Track, incoming: &Track) -> Option<Duration> {
+        let same_album = !outgoing.album.is_empty() && outgoing.album == incoming.album;
+        (!self.crossfade.is_zero()
+            && !same_album
+            && outgoing.path != incoming.path
+            && outgoing.total_duration > self.crossfade * 2
+            && incoming.total_duration > self.crossfade)
+            .then_some(self.crossfade)
     }
 
     #[instrument]
@@ -97,36 +669,432 @@ impl AudioPlayer {
 
     pub fn pause(&mut self) {
         self.sink.pause();
+        self.save_position();
+    }
+
+    /// Where a resumable track was left
+    fn resume_position(&self, track: &Track) -> Duration {
+        if !resume::is_resumable(track) {
+            return Duration::ZERO;
+        }
+        self.resume.position(&track.path).unwrap_or_default()
+    }
+
+    /// Saves where the current resumable track is
+    pub fn save_position(&mut self) {
+        if let Some(track) = self.resume_track.as_ref() {
+            let position = self.get_pos();
+            if let Err(err) = self.resume.set_position(track, position) {
+                trace_dbg!(&err);
+            }
+        }
+    }
+
+    /// Marks the start of a loop, then its end, then the start of a new loop
+    pub fn mark_loop(&mut self) {
+        let pos = self.get_pos();
+        match self.ab_loop.as_mut() {
+            Some(ab_loop) if ab_loop.b.is_none() && pos > ab_loop.a => ab_loop.b = Some(pos),
+            _ => {
+                self.ab_loop = Some(AbLoop {
+                    a: pos,
+                    b: None,
+                    slowdown: LOOP_SLOWDOWNS[0],
+                })
+            }
+        }
+        self.apply_loop_slowdown();
+    }
+
+    pub fn clear_loop(&mut self) {
+        self.ab_loop = None;
+        self.apply_loop_slowdown();
+    }
+
+    /// Switches to the next slowdown played while looping
+    pub fn cycle_loop_slowdown(&mut self) {
+        if let Some(ab_loop) = self.ab_loop.as_mut() {
+            let index = LOOP_SLOWDOWNS
+                .iter()
+                .position(|slowdown| *slowdown == ab_loop.slowdown)
+                .unwrap_or_default();
+            ab_loop.slowdown = LOOP_SLOWDOWNS[(index + 1) % LOOP_SLOWDOWNS.len()];
+        }
+        self.apply_loop_slowdown();
+    }
+
+    pub fn get_loop(&self) -> Option<&AbLoop> {
+        self.ab_loop.as_ref()
+    }
+
+    /// Slows playback down while a complete loop is set
+    fn apply_loop_slowdown(&self) {
+        self.speed.set_slowdown(match self.ab_loop {
+            Some(AbLoop {
+                b: Some(_),
+                slowdown,
+                ..
+            }) => slowdown,
+            _ => 1.0,
+        });
+    }
+
+    pub fn get_bookmarks(&self, path: &Path) -> &[Bookmark] {
+        self.resume.bookmarks(path)
+    }
+
+    /// Name a new bookmark of a track gets unless it is named
+    pub fn bookmark_name(&self, path: &Path) -> String {
+        self.resume.bookmark_name(path)
+    }
+
+    /// Bookmarks a position in a track
+    pub fn add_bookmark(&mut self, track: &Track, position: Duration, name: String) {
+        if let Err(err) = self.resume.add_bookmark(&track.path, position, name) {
+            trace_dbg!(&err);
+        }
+    }
+
+    pub fn remove_bookmark(&mut self, track: &Track, index: usize) {
+        if let Err(err) = self.resume.remove_bookmark(&track.path, index) {
+            trace_dbg!(&err);
+        }
+    }
+
+    /// Seeks to a position playing its track first if it is not the current one
+    pub fn play_from(&mut self, track: Track, position: Duration) -> color_eyre::Result<()> {
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|current| current.path == track.path)
+        {
+            return self.seek(position);
+        }
+        self.start_at = Some(position);
+        self.play_now(vec![track])
     }
 
     pub fn stop(&mut self) {
         self.sink.stop();
     }
 
+    /// Plays the next track, a track repeating on its own is left for the next one in the queue
     pub fn skip(&mut self) {
-        self.sink.skip_one();
+        let repeat = match self.repeat {
+            RepeatMode::One => RepeatMode::Off,
+            repeat => repeat,
+        };
+        if self.advance(repeat) {
+            self.start();
+        }
     }
 
-    pub fn restart(&mut self) {
-        // TODO
-        // seek beginning
+    /// Restarts the current track or, near its start, plays the last track in the history
+    pub fn previous(&mut self) -> color_eyre::Result<()> {
+        if self.get_pos() > PREVIOUS_RESTART_THRESHOLD {
+            return self.restart();
+        }
+        match self.history.pop() {
+            Some(track) => {
+                if let Some(current) = self.current.take() {
+                    self.queue.push(current);
+                }
+                self.queue.push(track);
+                self.play()
+            }
+            None => self.restart(),
+        }
+    }
+
+    pub fn restart(&mut self) -> color_eyre::Result<()> {
+        self.seek(Duration::ZERO)
+    }
+
+    /// Seeks the current track to `pos`, saturating at the end of the track
+    ///
+    /// Formats that cannot seek are re-decoded and skipped ahead instead
+    pub fn seek(&mut self, pos: Duration) -> color_eyre::Result<()> {
+        let Some(current) = self.current.as_ref() else {
+            return Ok(());
+        };
+        // A zero duration means it could not be read so do not clamp to it
+        let pos = if current.total_duration.is_zero() {
+            pos
+        } else {
+            pos.min(current.total_duration)
+        };
+
+        // Sources seek to positions in the whole track however far they were skipped ahead
+        if !self.sink.empty() {
+            match self.sink.try_seek(pos) {
+                Ok(()) => return Ok(()),
+                Err(err) => trace!("seek failed falling back to decode: {}", err),
+            }
+        }
+        self.seek_decode(pos)
+    }
+
+    /// Chapter of the current track being played
+    pub fn get_chapter(&self) -> Option<&Chapter> {
+        let current = self.current.as_ref()?;
+        current
+            .chapter_index_at(self.get_pos())
+            .and_then(|index| current.chapters.get(index))
+    }
+
+    /// Seeks to the next chapter or skips to the next track after the last one
+    pub fn next_chapter(&mut self) -> color_eyre::Result<()> {
+        let pos = self.get_pos();
+        let next = self.current.as_ref().and_then(|current| {
+            current
+                .chapters
+                .iter()
+                .find(|chapter| chapter.start > pos)
+                .map(|chapter| chapter.start)
+        });
+        match next {
+            Some(start) => self.seek(start),
+            None => {
+                self.skip();
+                Ok(())
+            }
+        }
+    }
+
+    /// Restarts the current chapter or, near its start, seeks to the previous one
+    ///
+    /// Tracks without chapters go to the previous track instead
+    pub fn previous_chapter(&mut self) -> color_eyre::Result<()> {
+        let pos = self.get_pos();
+        let start = self.current.as_ref().and_then(|current| {
+            let index = current.chapter_index_at(pos)?;
+            let start = current.chapters[index].start;
+            if pos.saturating_sub(start) > PREVIOUS_RESTART_THRESHOLD || index == 0 {
+                Some(start)
+            } else {
+                Some(current.chapters[index - 1].start)
+            }
+        });
+        match start {
+            Some(start) => self.seek(start),
+            None => self.previous(),
+        }
+    }
+
+    pub fn seek_forward(&mut self, by: Duration) -> color_eyre::Result<()> {
+        self.seek(self.get_pos().saturating_add(by))
+    }
+
+    pub fn seek_backward(&mut self, by: Duration) -> color_eyre::Result<()> {
+        self.seek(self.get_pos().saturating_sub(by))
+    }
+
+    /// Re-decodes the current track and skips ahead to `pos`
+    fn seek_decode(&mut self, pos: Duration) -> color_eyre::Result<()> {
+        if let Some(track) = self.current.as_ref() {
+            let paused = self.sink.is_paused();
+            let source = track.decode()?.skip_duration(pos);
+            self.cancel_prefetch();
+            self.sink.clear();
+            self.current_id = self.next_id();
+            self.current_handle = self.append(self.current_id, source, Arc::default(), pos);
+            self.current_appended = true;
+            self.finished = false;
+            self.outgoing = None;
+            self.offset = pos;
+            if !paused {
+                self.sink.play();
+            }
+        }
+        Ok(())
+    }
+
+    /// Position in the current track's content, unaffected by the playback speed
+    pub fn get_pos(&self) -> Duration {
+        Duration::from_nanos(self.current_handle.position.load(Ordering::Relaxed))
+    }
+
+    /// Removes the track at `index` of the queue
+    pub fn remove_queued(&mut self, index: usize) -> Option<Track> {
+        (index < self.queue.len()).then(|| self.queue.remove(index))
+    }
+
+    /// Moves the track at `index` one place closer to playing
+    pub fn move_queued_up(&mut self, index: usize) {
+        if index + 1 < self.queue.len() {
+            self.queue.swap(index, index + 1);
+        }
+    }
+
+    /// Moves the track at `index` one place further from playing
+    pub fn move_queued_down(&mut self, index: usize) {
+        if index > 0 && index < self.queue.len() {
+            self.queue.swap(index, index - 1);
+        }
+    }
+
+    /// Plays the track at `index` sending the current and skipped tracks to the history
+    pub fn jump_queued(&mut self, index: usize) -> color_eyre::Result<()> {
+        if index >= self.queue.len() {
+            return Ok(());
+        }
+        let skipped = self.queue.split_off(index + 1);
+        if let Some(current) = self.current.take() {
+            self.history.push(current);
+        }
+        self.history.extend(skipped.into_iter().rev());
+        self.play()
+    }
+
+    pub fn clear_queue(&mut self) {
+        self.queue.clear();
+    }
+
+    pub fn is_shuffled(&self) -> bool {
+        self.unshuffled.is_some()
+    }
+
+    /// Shuffles the queue or restores the order it had before shuffling
+    pub fn toggle_shuffle(&mut self) {
+        match self.unshuffled.take() {
+            Some(unshuffled) => {
+                // Keep the tracks that were not played or removed in their original order
+                let mut remaining = std::mem::take(&mut self.queue);
+                let mut restored = Vec::with_capacity(remaining.len());
+                for track in unshuffled {
+                    if let Some(index) = remaining.iter().position(|elem| elem.path == track.path) {
+                        restored.push(remaining.remove(index));
+                    }
+                }
+                // Tracks queued while shuffled play after the restored ones
+                remaining.append(&mut restored);
+                self.queue = remaining;
+            }
+            None => {
+                self.unshuffled = Some(self.queue.clone());
+                shuffle(&mut self.queue);
+            }
+        }
+    }
+
+    pub fn get_repeat(&self) -> RepeatMode {
+        self.repeat
+    }
+
+    pub fn cycle_repeat(&mut self) {
+        self.repeat = self.repeat.cycle();
+    }
+
+    pub fn volume_up(&mut self) {
+        self.set_volume(self.volume + VOLUME_STEP);
+    }
+
+    pub fn volume_down(&mut self) {
+        self.set_volume(self.volume - VOLUME_STEP);
+    }
+
+    /// Sets and saves the volume unmuting the player
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+        self.muted = false;
+        self.apply_volume();
+        if let Err(err) = save_volume(&self.data_dir, self.volume) {
+            trace_dbg!(err);
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.apply_volume();
+    }
+
+    /// Scales the volume without saving it, for fading out
+    pub fn set_fade(&mut self, fade: f32) {
+        self.fade = fade.clamp(0.0, 1.0);
+        self.apply_volume();
+    }
+
+    fn apply_volume(&self) {
+        self.sink.set_volume(if self.muted {
+            0.0
+        } else {
+            self.volume * self.fade
+        });
+    }
+
+    pub fn get_volume(&self) -> f32 {
+        self.volume
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    /// Gains applied to everything played, changes are heard immediately
+    pub fn equalizer(&self) -> &EqualizerControl {
+        &self.equalizer
+    }
+
+    pub fn speed_up(&mut self) {
+        self.speed.set_speed(self.speed.speed() + SPEED_STEP);
+    }
+
+    pub fn speed_down(&mut self) {
+        self.speed.set_speed(self.speed.speed() - SPEED_STEP);
+    }
+
+    pub fn reset_speed(&mut self) {
+        self.speed.set_speed(1.0);
+    }
+
+    pub fn get_speed(&self) -> f32 {
+        self.speed.speed()
+    }
+
+    /// Switches between keeping the pitch and letting it follow the speed
+    pub fn toggle_preserve_pitch(&mut self) {
+        self.speed.set_preserve_pitch(!self.speed.preserves_pitch());
+    }
+
+    pub fn preserves_pitch(&self) -> bool {
+        self.speed.preserves_pitch()
+    }
+
+    /// Name of the output being played to
+    pub fn output_name(&self) -> &'static str {
+        self.output.name()
     }
 
     pub fn get_progress(&self) -> f64 {
         match self.current.as_ref() {
-            Some(current) => self
-                .sink
+            // A zero duration means it could not be read
+            Some(current) if !current.total_duration.is_zero() => self
                 .get_pos()
                 .div_duration_f64(current.total_duration)
                 .clamp(0.0, 1.0),
-            None => 0.0,
+            _ => 0.0,
         }
     }
 
     pub fn get_progress_label(&self) -> String {
+        // Show both tracks while the previous one fades out
+        if let (Some((outgoing, crossfade)), Some(current)) =
+            (self.outgoing.as_ref(), self.current.as_ref())
+        {
+            let pos = self.get_pos();
+            if pos < *crossfade {
+                return format!(
+                    "{}|{} > {}|{}",
+                    (outgoing.total_duration.saturating_sub(*crossfade) + pos).hhmmss(),
+                    outgoing.total_duration.hhmmss(),
+                    pos.hhmmss(),
+                    current.total_duration.hhmmss(),
+                );
+            }
+        }
         format!(
             "{}|{}",
-            self.sink.get_pos().hhmmss(),
+            self.get_pos().hhmmss(),
             match self.current.as_ref() {
                 Some(current) => {
                     current.total_duration.hhmmss()
@@ -149,6 +1117,437 @@ impl<'a> AudioPlayer {
     pub fn get_current(&'a self) -> Option<&'a Track> {
         self.current.as_ref()
     }
+
+    pub fn get_warning(&'a self) -> Option<&'a str> {
+        self.warning.as_deref()
+    }
+
+    /// Shows a warning to the user in place of the last one
+    pub fn set_warning(&mut self, warning: impl Into<String>) {
+        self.warning = Some(warning.into());
+    }
+
+    /// Queued tracks, the last one plays next
+    pub fn get_queue(&'a self) -> &'a [Track] {
+        &self.queue
+    }
+
+    /// Played tracks, the last one played most recently
+    pub fn get_history(&'a self) -> &'a [Track] {
+        &self.history
+    }
+}
+
+/// Decodes the start of `incoming` mixed with the faded out tail of `outgoing`
+fn decode_crossfade(
+    outgoing: &Track,
+    incoming: &Track,
+    duration: Duration,
+) -> color_eyre::Result<BoxedSource> {
+    let cut = outgoing.total_duration.saturating_sub(duration);
+    let mut tail = outgoing.decode()?;
+    let tail: BoxedSource = match tail.try_seek(cut) {
+        Ok(()) => Box::new(tail),
+        Err(_) => Box::new(outgoing.decode()?.skip_duration(cut)),
+    };
+    Ok(Box::new(
+        incoming
+            .decode()?
+            .fade_in(duration)
+            .mix(tail.take_duration(duration).fade_out(duration)),
+    ))
+}
+
+/// Source reporting to the player when it plays to its end
+struct Tracked<S> {
+    inner: S,
+    id: u64,
+    cancel: Arc<AtomicBool>,
+    handle: SourceHandle,
+    /// Samples played counting from the start of the track
+    played: u64,
+    sender: Sender<PlayerMessage>,
+    done: bool,
+}
+
+impl<S: Source> Tracked<S> {
+    /// Nanoseconds into the track played so far
+    fn played_nanos(&self) -> u64 {
+        let rate = self.inner.sample_rate() as u128 * self.inner.channels() as u128;
+        if rate == 0 {
+            return 0;
+        }
+        (self.played as u128 * 1_000_000_000 / rate) as u64
+    }
+}
+
+impl<S: Source> Iterator for Tracked<S> {
+    type Item = S::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+        let position = self.played_nanos();
+        self.handle.position.store(position, Ordering::Relaxed);
+        let end = self.handle.end.load(Ordering::Relaxed);
+        let sample = self.inner.next().filter(|_| position < end);
+        match sample {
+            Some(_) => self.played += 1,
+            None => {
+                self.done = true;
+                let _ = self.sender.send(PlayerMessage::Finished(self.id));
+            }
+        }
+        sample
+    }
+}
+
+impl<S: Source> Source for Tracked<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+
+    fn channels(&self) -> rodio::ChannelCount {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> rodio::SampleRate {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.played = (pos.as_secs_f64() * self.inner.sample_rate() as f64) as u64
+            * self.inner.channels() as u64;
+        self.handle
+            .position
+            .store(self.played_nanos(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Source starting `start` into its track, skipping ahead when it cannot seek
+fn skip_to<S>(mut source: S, start: Duration) -> BoxedSource
+where
+    S: Source + Send + 'static,
+{
+    if start.is_zero() || source.try_seek(start).is_ok() {
+        Box::new(source)
+    } else {
+        Box::new(source.skip_duration(start))
+    }
+}
+
+/// Loads the last saved volume defaulting to full volume
+fn load_volume(directory: &Path) -> f32 {
+    std::fs::read_to_string(directory.join(VOLUME_FILE))
+        .ok()
+        .and_then(|volume| volume.trim().parse::<f32>().ok())
+        .unwrap_or(1.0)
+        .clamp(0.0, 1.0)
+}
+
+fn save_volume(directory: &Path, volume: f32) -> std::io::Result<()> {
+    std::fs::create_dir_all(directory)?;
+    std::fs::write(directory.join(VOLUME_FILE), volume.to_string())
+}
+
+/// Fisher–Yates shuffle seeded from the clock
+fn shuffle<T>(items: &mut [T]) {
+    let mut state = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default()
+        | 1;
+    for index in (1..items.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        items.swap(index, (state % (index as u64 + 1)) as usize);
+    }
+}
+
+/// Track in the queue along with its index in [`AudioPlayer::get_queue`]
+#[derive(Clone)]
+pub struct QueueEntry {
+    pub index: usize,
+    pub position: usize,
+    pub track: Track,
+}
+
+impl Item for QueueEntry {}
+
+impl Into<AppEvent> for QueueEntry {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(move || {
+            LinkedMenu::new(Box::new(MenuFrame::new([
+                Box::new(
+                    TableMenu::new(
+                        vec![
+                            AppEvent::QueueJump(self.index),
+                            AppEvent::QueueMoveUp(self.index),
+                            AppEvent::QueueMoveDown(self.index),
+                            AppEvent::QueueRemove(self.index),
+                        ],
+                        [Constraint::Fill(100)],
+                    )
+                    .with_header(Row::new([Cell::new(self.track.title.clone())])),
+                ),
+                quick_menu(),
+            ])))
+        }))
+    }
+}
+
+impl<'a> Into<Row<'a>> for QueueEntry {
+    fn into(self) -> Row<'a> {
+        [
+            self.position.to_string(),
+            self.track.title.clone(),
+            self.track.artist.clone(),
+            self.track.total_duration.hhmmss(),
+        ]
+        .into_iter()
+        .chain(stats::track_cells(&self.track.path))
+        .map(|elem| Cell::from(Text::from(elem)))
+        .collect()
+    }
 }
 
-// TODO make a history/queue menu
+#[derive(Clone)]
+pub struct QueueItem;
+
+impl Item for QueueItem {}
+
+impl QueueItem {
+    pub fn to_menu(self) -> TableMenu<QueueItem, [Constraint; 1]> {
+        TableMenu::new(vec![self], [Constraint::Fill(100)])
+    }
+}
+
+impl Into<AppEvent> for QueueItem {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(|| queue_menu()))
+    }
+}
+
+impl<'a> Into<Row<'a>> for QueueItem {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new("Queue")])
+    }
+}
+
+/// Menu of the current track, the upcoming queue in play order and the recent history
+pub fn queue_menu() -> LinkedMenu {
+    LinkedMenu::new(Box::new(MenuFrame::new([
+        Box::new(
+            TableMenu::new(vec![], track_widths())
+                .with_header(track_header("Playing"))
+                .with_ticker(|items, app_state| {
+                    items.clear();
+                    items.extend(app_state.player.get_current().cloned());
+                    Ok(())
+                }),
+        ),
+        Box::new(
+            TableMenu::new(
+                vec![],
+                [Constraint::Length(3)]
+                    .into_iter()
+                    .chain(track_widths())
+                    .collect::<Vec<_>>(),
+            )
+            .with_header(Row::new(
+                ["#", "Queue", "Artist", "Duration"]
+                    .into_iter()
+                    .map(Cell::new)
+                    .chain(stats::header()),
+            ))
+            .with_ticker(|items, app_state| {
+                items.clear();
+                items.extend(
+                    app_state
+                        .player
+                        .get_queue()
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .enumerate()
+                        .map(|(position, (index, track))| QueueEntry {
+                            index,
+                            position: position + 1,
+                            track: track.clone(),
+                        }),
+                );
+                Ok(())
+            }),
+        ),
+        Box::new(
+            TableMenu::new(vec![], track_widths())
+                .with_header(track_header("History"))
+                .with_ticker(|items, app_state| {
+                    items.clear();
+                    items.extend(app_state.player.get_history().iter().rev().cloned());
+                    Ok(())
+                }),
+        ),
+        Box::new(TableMenu::new(
+            vec![AppEvent::ClearQueue],
+            [Constraint::Fill(100)],
+        )),
+        quick_menu(),
+    ])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{output::NullOutput, replaygain::ReplayGain};
+
+    fn track(name: &str) -> Track {
+        Track {
+            path: PathBuf::from(name),
+            title: name.to_string(),
+            artist: String::new(),
+            album: String::new(),
+            genre: String::new(),
+            year: None,
+            track_number: None,
+            total_duration: Duration::ZERO,
+            replay_gain: ReplayGain::default(),
+            chapters: Arc::from([]),
+            modified: None,
+        }
+    }
+
+    fn tracks(names: &[&str]) -> Vec<Track> {
+        names.iter().map(|name| track(name)).collect()
+    }
+
+    /// Silent player saving to its own dir in the temp dir
+    fn player(name: &str) -> AudioPlayer {
+        let data_dir = std::env::temp_dir().join(format!(
+            "{}-{}-{name}",
+            env!("CARGO_PKG_NAME"),
+            std::process::id()
+        ));
+        AudioPlayer::with_output(Box::new(NullOutput::new()), data_dir)
+    }
+
+    /// Titles of the tracks advanced through, at most `limit` of them
+    fn advance_titles(player: &mut AudioPlayer, repeat: RepeatMode, limit: usize) -> Vec<String> {
+        let mut titles = vec![];
+        while titles.len() < limit && player.advance(repeat) {
+            titles.extend(player.get_current().map(|track| track.title.clone()));
+        }
+        titles
+    }
+
+    fn titles(tracks: &[Track]) -> Vec<String> {
+        tracks.iter().map(|track| track.title.clone()).collect()
+    }
+
+    #[test]
+    fn enqueue_plays_after_queued_tracks() {
+        let mut player = player("enqueue");
+        player.enqueue(tracks(&["a", "b"]));
+        player.enqueue(tracks(&["c"]));
+        assert_eq!(
+            advance_titles(&mut player, RepeatMode::Off, 10),
+            ["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn play_next_plays_before_queued_tracks() {
+        let mut player = player("play_next");
+        player.enqueue(tracks(&["a", "b"]));
+        player.play_next(tracks(&["c", "d"]));
+        assert_eq!(
+            advance_titles(&mut player, RepeatMode::Off, 10),
+            ["c", "d", "a", "b"]
+        );
+    }
+
+    #[test]
+    fn advance_moves_played_tracks_to_history() {
+        let mut player = player("advance");
+        player.enqueue(tracks(&["a", "b", "c"]));
+        assert_eq!(advance_titles(&mut player, RepeatMode::Off, 10).len(), 3);
+        assert_eq!(titles(player.get_history()), ["a", "b"]);
+        assert!(player.get_queue().is_empty());
+        assert!(!player.advance(RepeatMode::Off));
+        assert_eq!(
+            player.get_current().map(|track| track.title.as_str()),
+            Some("c")
+        );
+    }
+
+    #[test]
+    fn repeat_one_keeps_the_current_track() {
+        let mut player = player("repeat_one");
+        player.enqueue(tracks(&["a", "b"]));
+        assert_eq!(
+            advance_titles(&mut player, RepeatMode::One, 3),
+            ["a", "a", "a"]
+        );
+        assert!(player.get_history().is_empty());
+        assert_eq!(titles(player.get_queue()), ["b"]);
+    }
+
+    #[test]
+    fn repeat_all_starts_over_from_the_history() {
+        let mut player = player("repeat_all");
+        player.enqueue(tracks(&["a", "b", "c"]));
+        assert_eq!(
+            advance_titles(&mut player, RepeatMode::All, 7),
+            ["a", "b", "c", "a", "b", "c", "a"]
+        );
+    }
+
+    #[test]
+    fn progress_of_unknown_length_is_zero() {
+        let mut player = player("progress");
+        player.enqueue(tracks(&["a"]));
+        assert!(player.advance(RepeatMode::Off));
+        assert_eq!(player.get_progress(), 0.0);
+    }
+
+    #[test]
+    fn shuffle_keeps_the_queued_tracks() {
+        let mut player = player("shuffle");
+        let names: Vec<String> = (0..20).map(|index| index.to_string()).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        player.enqueue(tracks(&names));
+        player.toggle_shuffle();
+        assert!(player.is_shuffled());
+        let mut shuffled = titles(player.get_queue());
+        shuffled.sort();
+        let mut expected = titles(&tracks(&names));
+        expected.sort();
+        assert_eq!(shuffled, expected);
+    }
+
+    #[test]
+    fn unshuffle_restores_the_order_of_unplayed_tracks() {
+        let mut player = player("unshuffle");
+        player.enqueue(tracks(&["a", "b", "c", "d"]));
+        player.toggle_shuffle();
+        let played = advance_titles(&mut player, RepeatMode::Off, 2);
+        player.enqueue(tracks(&["e"]));
+        player.toggle_shuffle();
+        assert!(!player.is_shuffled());
+        let expected: Vec<&str> = ["a", "b", "c", "d"]
+            .into_iter()
+            .filter(|name| !played.iter().any(|played| played == name))
+            .chain(["e"])
+            .collect();
+        assert_eq!(advance_titles(&mut player, RepeatMode::Off, 10), expected);
+    }
+}
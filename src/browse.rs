@@ -0,0 +1,347 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use hhmmss::Hhmmss;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    text::Text,
+    widgets::{Cell, Row},
+};
+use strum_macros::Display;
+
+use crate::{
+    app::{AppState, quick_menu},
+    event::{AppEvent, FsEvent},
+    library::LIBRARY,
+    menus::{Item, LinkedMenu, Menu, MenuFrame, NavigationResult, TableMenu},
+    track::{Track, track_header, track_widths},
+};
+
+/// How a group of the library was grouped and what it drills down to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum GroupKind {
+    Artist,
+    Album,
+    Genre,
+    Year,
+}
+
+impl GroupKind {
+    /// Name of the group a track belongs to, unknown when it does not have the tag
+    fn name(self, track: &Track) -> String {
+        let name = match self {
+            Self::Artist => track.artist.clone(),
+            Self::Album => track.album.clone(),
+            Self::Genre => track.genre.clone(),
+            Self::Year => track.year.map(|year| year.to_string()).unwrap_or_default(),
+        };
+        if name.is_empty() {
+            String::from("Unknown")
+        } else {
+            name
+        }
+    }
+}
+
+/// Groups a table of the library was drilled down through, none for the whole library
+#[derive(Debug, Clone, Default)]
+pub struct Scope(Vec<(GroupKind, String)>);
+
+impl Scope {
+    fn with(&self, kind: GroupKind, name: String) -> Self {
+        let mut scope = self.clone();
+        scope.0.push((kind, name));
+        scope
+    }
+
+    fn contains(&self, track: &Track) -> bool {
+        self.0.iter().all(|(kind, name)| &kind.name(track) == name)
+    }
+
+    /// Tracks of the library in the scope
+    fn tracks(&self) -> Vec<Track> {
+        LIBRARY
+            .tracks()
+            .iter()
+            .filter(|track| self.contains(track))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Tracks of the library sharing an artist, album, genre or year
+#[derive(Clone)]
+pub struct LibraryGroup {
+    pub kind: GroupKind,
+    pub name: String,
+    pub tracks: Vec<Track>,
+    /// Scope of the group's tracks
+    scope: Scope,
+}
+
+impl LibraryGroup {
+    /// Groups the tracks of a scope by a tag, tracks without it are grouped as unknown
+    pub fn group(kind: GroupKind, scope: &Scope, tracks: &[Track]) -> Vec<Self> {
+        let mut groups = BTreeMap::<String, Vec<Track>>::new();
+        for track in tracks {
+            groups
+                .entry(kind.name(track))
+                .or_default()
+                .push(track.clone());
+        }
+        groups
+            .into_iter()
+            .map(|(name, tracks)| Self {
+                kind,
+                scope: scope.with(kind, name.clone()),
+                name,
+                tracks,
+            })
+            .collect()
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        self.tracks
+            .iter()
+            .fold(Duration::default(), |acc, elem| acc + elem.total_duration)
+    }
+}
+
+impl Item for LibraryGroup {}
+
+impl<'a> Into<Row<'a>> for LibraryGroup {
+    fn into(self) -> Row<'a> {
+        [
+            self.name.clone(),
+            self.tracks.len().to_string(),
+            self.get_duration().hhmmss(),
+        ]
+        .into_iter()
+        .map(|elem| Cell::from(Text::from(elem)))
+        .collect()
+    }
+}
+
+impl Into<AppEvent> for LibraryGroup {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(move || group_menu(self.clone())))
+    }
+}
+
+/// Play actions for a group above the level it drills down to, albums for artists and tracks
+/// for everything else
+fn group_menu(group: LibraryGroup) -> LinkedMenu {
+    let actions = Box::new(
+        TableMenu::new(
+            vec![
+                AppEvent::PlayNow(group.tracks.clone()),
+                AppEvent::Enqueue(group.tracks.clone()),
+                AppEvent::PlayNext(group.tracks.clone()),
+                AppEvent::AddToPlaylist(group.tracks.clone()),
+            ],
+            [Constraint::Fill(100)],
+        )
+        .with_header(Row::new([Cell::new(format!(
+            "{}: {}",
+            group.kind, group.name
+        ))])),
+    );
+    match group.kind {
+        GroupKind::Artist => LinkedMenu::new(Box::new(MenuFrame::new([
+            actions,
+            Box::new(group_table(GroupKind::Album, group.scope)),
+            quick_menu(),
+        ]))),
+        _ => LinkedMenu::new(Box::new(MenuFrame::new([
+            actions,
+            Box::new(track_table(group.scope)),
+            quick_menu(),
+        ]))),
+    }
+}
+
+/// Rows of a library table for the tracks of its scope
+type Rows<T> = Box<dyn Fn(&Scope, &[Track]) -> Vec<T>>;
+
+/// Table of the tracks of a scope, rebuilt from the library whenever its tracks change
+///
+/// New and retagged tracks move into the tables and groups they now belong to
+struct LibraryTable<T: Item> {
+    scope: Scope,
+    rows: Rows<T>,
+    table: TableMenu<T, Vec<Constraint>>,
+}
+
+impl<T: Item> LibraryTable<T> {
+    fn new(
+        scope: Scope,
+        rows: Rows<T>,
+        widths: Vec<Constraint>,
+        header: Row<'static>,
+        sorts: Vec<fn(&T, &T) -> std::cmp::Ordering>,
+    ) -> Self {
+        let table = TableMenu::new(rows(&scope, &scope.tracks()), widths)
+            .with_header(header)
+            .with_sorts(sorts);
+        Self { scope, rows, table }
+    }
+}
+
+impl<T: Item> Menu for LibraryTable<T> {
+    fn up(&mut self) -> NavigationResult {
+        self.table.up()
+    }
+
+    fn down(&mut self) -> NavigationResult {
+        self.table.down()
+    }
+
+    fn sort(&mut self) -> bool {
+        self.table.sort()
+    }
+
+    fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
+        self.table.enter()
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, focused: bool) {
+        self.table.render(area, buf, focused);
+    }
+
+    fn constraint(&self) -> Constraint {
+        self.table.constraint()
+    }
+
+    fn tick(&mut self, app_state: &AppState) -> color_eyre::Result<()> {
+        if app_state.fs_events.iter().any(|event| {
+            matches!(
+                event,
+                FsEvent::TrackChanged(_) | FsEvent::TrackRemoved(_) | FsEvent::LibraryScanned
+            )
+        }) {
+            self.table
+                .set_items((self.rows)(&self.scope, &self.scope.tracks()));
+        }
+        Ok(())
+    }
+}
+
+/// Groups of a scope sortable by name, most tracks or longest duration
+fn group_table(kind: GroupKind, scope: Scope) -> LibraryTable<LibraryGroup> {
+    LibraryTable::new(
+        scope,
+        Box::new(move |scope, tracks| LibraryGroup::group(kind, scope, tracks)),
+        vec![
+            Constraint::Min(5),
+            Constraint::Length(6),
+            Constraint::Length(8),
+        ],
+        Row::new([
+            Cell::new(format!("{kind}")),
+            Cell::new("Tracks"),
+            Cell::new("Duration"),
+        ]),
+        vec![
+            |a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            |a, b| b.tracks.len().cmp(&a.tracks.len()),
+            |a, b| b.get_duration().cmp(&a.get_duration()),
+        ],
+    )
+}
+
+/// Tracks of a scope in album order sortable by title, artist or longest duration
+fn track_table(scope: Scope) -> LibraryTable<Track> {
+    LibraryTable::new(
+        scope,
+        Box::new(|_, tracks| tracks.to_vec()),
+        track_widths(),
+        track_header("Title"),
+        vec![
+            |a, b| (&a.album, a.track_number, &a.path).cmp(&(&b.album, b.track_number, &b.path)),
+            |a, b| a.title.to_lowercase().cmp(&b.title.to_lowercase()),
+            |a, b| a.artist.to_lowercase().cmp(&b.artist.to_lowercase()),
+            |a, b| b.total_duration.cmp(&a.total_duration),
+        ],
+    )
+}
+
+/// Top level ways of browsing the library
+#[derive(Debug, Clone, Copy, Display)]
+pub enum LibraryView {
+    Artists,
+    Albums,
+    Genres,
+    Years,
+    Tracks,
+}
+
+impl Item for LibraryView {}
+
+impl<'a> Into<Row<'a>> for LibraryView {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new(self.to_string())])
+    }
+}
+
+impl Into<AppEvent> for LibraryView {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(move || {
+            let scope = Scope::default();
+            let table: Box<dyn Menu> = match self {
+                Self::Artists => Box::new(group_table(GroupKind::Artist, scope)),
+                Self::Albums => Box::new(group_table(GroupKind::Album, scope)),
+                Self::Genres => Box::new(group_table(GroupKind::Genre, scope)),
+                Self::Years => Box::new(group_table(GroupKind::Year, scope)),
+                Self::Tracks => Box::new(track_table(scope)),
+            };
+            LinkedMenu::new(Box::new(MenuFrame::new([table, quick_menu()])))
+        }))
+    }
+}
+
+#[derive(Clone)]
+pub struct LibraryItem;
+
+impl Item for LibraryItem {}
+
+impl LibraryItem {
+    pub fn to_menu(self) -> TableMenu<LibraryItem, [Constraint; 1]> {
+        TableMenu::new(vec![self], [Constraint::Fill(100)])
+    }
+}
+
+impl Into<AppEvent> for LibraryItem {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(|| library_menu()))
+    }
+}
+
+impl<'a> Into<Row<'a>> for LibraryItem {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new("Library")])
+    }
+}
+
+/// Views of the library, `o` cycles the order of their tables
+pub fn library_menu() -> LinkedMenu {
+    LinkedMenu::new(Box::new(MenuFrame::new([
+        Box::new(
+            TableMenu::new(
+                vec![
+                    LibraryView::Artists,
+                    LibraryView::Albums,
+                    LibraryView::Genres,
+                    LibraryView::Years,
+                    LibraryView::Tracks,
+                ],
+                [Constraint::Fill(100)],
+            )
+            .with_header(Row::new([Cell::new("Library")])),
+        ),
+        Box::new(TableMenu::new(
+            vec![AppEvent::RescanLibrary],
+            [Constraint::Fill(100)],
+        )),
+        quick_menu(),
+    ])))
+}
@@ -0,0 +1,352 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use hhmmss::Hhmmss;
+use ratatui::{
+    layout::Constraint,
+    text::Text,
+    widgets::{Cell, Row},
+};
+
+use crate::{
+    event::AppEvent,
+    menus::{Item, TableMenu},
+    track::{Track, read_id3},
+};
+
+/// Cue sheet timestamps count frames of a CD
+const CUE_FRAMES_PER_SECOND: f64 = 75.0;
+/// Larger chapter lists are assumed to be corrupt
+const MAX_CHPL_LEN: u64 = 1 << 20;
+
+#[derive(Debug, Clone)]
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+}
+
+/// Chapters from a sidecar cue sheet or else the ones embedded in the file, ordered by start
+pub fn read(path: &Path) -> Vec<Chapter> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let mut chapters = cue_chapters(path)
+        .or_else(|| match extension.as_str() {
+            "m4a" | "m4b" | "mp4" => mp4_chapters(path),
+            "mp3" | "wav" | "aiff" | "aif" => id3_chapters(path),
+            _ => None,
+        })
+        .unwrap_or_default();
+    chapters.sort_by_key(|chapter| chapter.start);
+    chapters
+}
+
+/// Chapters of the `.cue` sheet named after the file with or without its extension
+fn cue_chapters(path: &Path) -> Option<Vec<Chapter>> {
+    let file_name = path.file_name()?.to_str()?;
+    let sheet = [
+        path.with_extension("cue"),
+        PathBuf::from(format!("{}.cue", path.display())),
+    ]
+    .iter()
+    .find_map(|cue| std::fs::read_to_string(cue).ok())?;
+    let chapters = parse_cue(&sheet, file_name);
+    (!chapters.is_empty()).then_some(chapters)
+}
+
+/// Tracks of a cue sheet that belong to `file_name`, or all of them when it names one file
+fn parse_cue(sheet: &str, file_name: &str) -> Vec<Chapter> {
+    let single_file = sheet
+        .lines()
+        .filter(|line| line.trim_start().to_uppercase().starts_with("FILE "))
+        .count()
+        <= 1;
+    let mut matching = single_file;
+    let mut number = None;
+    let mut title = None;
+    let mut chapters = vec![];
+    for line in sheet.lines() {
+        let line = line.trim();
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match command.to_uppercase().as_str() {
+            "FILE" => {
+                // The file name is followed by its type
+                let name = rest.rsplit_once(' ').map_or(rest, |(name, _)| name);
+                matching = single_file || unquote(name) == file_name;
+                number = None;
+            }
+            "TRACK" => {
+                number = rest.split_whitespace().next().map(str::to_string);
+                title = None;
+            }
+            "TITLE" if number.is_some() => title = Some(unquote(rest).to_string()),
+            "INDEX" if matching => {
+                let mut fields = rest.split_whitespace();
+                if let (Some("01"), Some(start)) = (fields.next(), fields.next()) {
+                    chapters.extend(parse_cue_time(start).map(|start| Chapter {
+                        title: title.clone().unwrap_or_else(|| {
+                            format!("Track {}", number.clone().unwrap_or_default())
+                        }),
+                        start,
+                    }));
+                }
+            }
+            _ => {}
+        }
+    }
+    chapters
+}
+
+fn unquote(text: &str) -> &str {
+    text.trim().trim_matches('"')
+}
+
+/// Parses a `mm:ss:ff` cue timestamp
+fn parse_cue_time(time: &str) -> Option<Duration> {
+    let mut fields = time.split(':').map(|field| field.parse::<u64>().ok());
+    let (minutes, seconds, frames) = (fields.next()??, fields.next()??, fields.next()??);
+    Some(Duration::from_secs_f64(
+        (minutes * 60 + seconds) as f64 + frames as f64 / CUE_FRAMES_PER_SECOND,
+    ))
+}
+
+/// Chapters of the ID3 tag in the order of its top level table of contents if it has one
+fn id3_chapters(path: &Path) -> Option<Vec<Chapter>> {
+    let tag = read_id3(path)?;
+    let mut chapters = tag
+        .chapters()
+        .map(|chapter| {
+            let title = chapter
+                .frames
+                .iter()
+                .find(|frame| frame.id() == "TIT2")
+                .and_then(|frame| frame.content().text())
+                .map_or_else(|| chapter.element_id.clone(), str::to_string);
+            (
+                chapter.element_id.as_str(),
+                Chapter {
+                    title,
+                    start: Duration::from_millis(chapter.start_time as u64),
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let chapters = match tag.tables_of_contents().find(|toc| toc.top_level) {
+        Some(toc) => toc
+            .elements
+            .iter()
+            .filter_map(|element| chapters.remove(element.as_str()))
+            .collect::<Vec<_>>(),
+        None => chapters.into_values().collect(),
+    };
+    (!chapters.is_empty()).then_some(chapters)
+}
+
+/// Chapters of the Nero `chpl` atom in `moov.udta`
+fn mp4_chapters(path: &Path) -> Option<Vec<Chapter>> {
+    let mut file = BufReader::new(File::open(path).ok()?);
+    let len = file.get_ref().metadata().ok()?.len();
+    let (start, end) = [b"moov", b"udta", b"chpl"]
+        .iter()
+        .try_fold((0, len), |(start, end), kind| {
+            find_atom(&mut file, start, end, kind)
+        })?;
+    if end - start > MAX_CHPL_LEN {
+        return None;
+    }
+    file.seek(SeekFrom::Start(start)).ok()?;
+    let mut data = vec![0; (end - start) as usize];
+    file.read_exact(&mut data).ok()?;
+    parse_chpl(&data)
+}
+
+/// Content of the first atom of a kind between two offsets
+fn find_atom<R: Read + Seek>(
+    reader: &mut R,
+    mut offset: u64,
+    end: u64,
+    kind: &[u8; 4],
+) -> Option<(u64, u64)> {
+    while offset + 8 <= end {
+        reader.seek(SeekFrom::Start(offset)).ok()?;
+        let mut header = [0; 8];
+        reader.read_exact(&mut header).ok()?;
+        let (mut size, mut header_len) =
+            (u32::from_be_bytes(header[..4].try_into().ok()?) as u64, 8);
+        if size == 1 {
+            let mut large = [0; 8];
+            reader.read_exact(&mut large).ok()?;
+            (size, header_len) = (u64::from_be_bytes(large), 16);
+        } else if size == 0 {
+            size = end - offset;
+        }
+        if size < header_len {
+            return None;
+        }
+        if &header[4..] == kind {
+            return Some((offset + header_len, offset.saturating_add(size).min(end)));
+        }
+        offset = offset.saturating_add(size);
+    }
+    None
+}
+
+/// Parses a `chpl` atom, starts are in units of 100ns
+fn parse_chpl(data: &[u8]) -> Option<Vec<Chapter>> {
+    let version = *data.first()?;
+    // Version and flags followed by a reserved field in later versions
+    let mut data = data.get(if version == 0 { 4 } else { 8 }..)?;
+    let count = *data.first()?;
+    data = &data[1..];
+    let mut chapters = vec![];
+    for _ in 0..count {
+        let start = u64::from_be_bytes(data.get(..8)?.try_into().ok()?);
+        let len = *data.get(8)? as usize;
+        let title = String::from_utf8_lossy(data.get(9..9 + len)?).to_string();
+        data = &data[9 + len..];
+        chapters.push(Chapter {
+            title,
+            start: Duration::from_nanos(start.saturating_mul(100)),
+        });
+    }
+    (!chapters.is_empty()).then_some(chapters)
+}
+
+#[derive(Clone)]
+pub struct ChapterEntry {
+    pub number: usize,
+    pub chapter: Chapter,
+    pub track: Track,
+}
+
+impl Item for ChapterEntry {}
+
+impl Into<AppEvent> for ChapterEntry {
+    fn into(self) -> AppEvent {
+        AppEvent::ChapterJump(self.track, self.chapter.start)
+    }
+}
+
+impl<'a> Into<Row<'a>> for ChapterEntry {
+    fn into(self) -> Row<'a> {
+        [
+            self.number.to_string(),
+            self.chapter.title.clone(),
+            self.chapter.start.hhmmss(),
+        ]
+        .iter()
+        .map(|elem| Cell::from(Text::from(elem.clone())))
+        .collect()
+    }
+}
+
+/// Chapters of a track, each playing the track from its start
+pub fn chapter_menu(track: &Track) -> TableMenu<ChapterEntry, [Constraint; 3]> {
+    TableMenu::new(
+        track
+            .chapters
+            .iter()
+            .enumerate()
+            .map(|(index, chapter)| ChapterEntry {
+                number: index + 1,
+                chapter: chapter.clone(),
+                track: track.clone(),
+            })
+            .collect(),
+        [
+            Constraint::Length(3),
+            Constraint::Min(5),
+            Constraint::Length(8),
+        ],
+    )
+    .with_header(Row::new([
+        Cell::new("#"),
+        Cell::new("Chapters"),
+        Cell::new("Start"),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(chapters: &[Chapter]) -> Vec<(&str, Duration)> {
+        chapters
+            .iter()
+            .map(|chapter| (chapter.title.as_str(), chapter.start))
+            .collect()
+    }
+
+    #[test]
+    fn cue_of_a_single_file_applies_to_any_name() {
+        let sheet = "TITLE \"Album\"\nFILE \"album.flac\" WAVE\n  TRACK 01 AUDIO\n    TITLE \"Intro\"\n    INDEX 00 00:00:00\n    INDEX 01 00:00:00\n  TRACK 02 AUDIO\n    INDEX 01 01:02:37\n";
+        assert_eq!(
+            starts(&parse_cue(sheet, "renamed.flac")),
+            [
+                ("Intro", Duration::ZERO),
+                ("Track 02", Duration::from_secs_f64(62.0 + 37.0 / 75.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn cue_of_several_files_keeps_the_named_one() {
+        let sheet = "FILE \"one.mp3\" MP3\n  TRACK 01 AUDIO\n    TITLE \"A\"\n    INDEX 01 00:00:00\nFILE \"two.mp3\" MP3\n  TRACK 02 AUDIO\n    TITLE \"B\"\n    INDEX 01 00:10:00\n";
+        assert_eq!(
+            starts(&parse_cue(sheet, "two.mp3")),
+            [("B", Duration::from_secs(10))]
+        );
+        assert!(parse_cue(sheet, "three.mp3").is_empty());
+    }
+
+    #[test]
+    fn cue_skips_malformed_times() {
+        let sheet = "FILE \"a.mp3\" MP3\n  TRACK 01 AUDIO\n    INDEX 01 00:xx:00\n";
+        assert!(parse_cue(sheet, "a.mp3").is_empty());
+    }
+
+    /// `chpl` atom data with the chapter starts in units of 100ns
+    fn chpl(version: u8, chapters: &[(u64, &str)]) -> Vec<u8> {
+        let mut data = vec![version, 0, 0, 0];
+        if version != 0 {
+            data.extend([0; 4]);
+        }
+        data.push(chapters.len() as u8);
+        for (start, title) in chapters {
+            data.extend(start.to_be_bytes());
+            data.push(title.len() as u8);
+            data.extend(title.as_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn chpl_reads_both_versions() {
+        for version in [0, 1] {
+            let chapters = parse_chpl(&chpl(version, &[(0, "One"), (15_000_000, "Two")])).unwrap();
+            assert_eq!(
+                starts(&chapters),
+                [
+                    ("One", Duration::ZERO),
+                    ("Two", Duration::from_millis(1500))
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn chpl_rejects_truncated_data() {
+        let mut data = chpl(1, &[(0, "One"), (10, "Two")]);
+        data.truncate(data.len() - 2);
+        assert!(parse_chpl(&data).is_none());
+        assert!(parse_chpl(&chpl(1, &[])).is_none());
+        assert!(parse_chpl(&[]).is_none());
+    }
+}
@@ -1,11 +1,43 @@
+use std::{collections::BTreeMap, path::PathBuf, time::Duration};
+
 use color_eyre::eyre::OptionExt;
 use serde::Deserialize;
 
-use crate::playlist::Playlist;
+use crate::{
+    equalizer::Gains, output::OutputConfig, playlist::Playlist, playlist_formats::is_playlist,
+    replaygain::ReplayGainMode,
+};
 
 #[derive(Deserialize, Debug)]
 pub struct Config {
     pub music_dir: String,
+    /// Where audio is played, `"device"`, `"null"` or `{ wav = "path" }`
+    #[serde(default)]
+    pub output: OutputConfig,
+    /// Seconds the end of a track overlaps the start of the next
+    #[serde(default)]
+    pub crossfade: f64,
+    /// ReplayGain tag applied to tracks, `"off"`, `"track"` or `"album"`
+    #[serde(default)]
+    pub replaygain: ReplayGainMode,
+    /// Analyse the loudness of tracks without ReplayGain tags in the background
+    #[serde(default)]
+    pub loudness_analysis: bool,
+    /// Equalizer presets by name with a gain in dB for each band
+    #[serde(default)]
+    pub eq_presets: BTreeMap<String, Gains>,
+    /// Minutes long a track has to be to resume where it was left
+    #[serde(default)]
+    pub resume_minutes: Option<f64>,
+    /// Audiobook and podcast directories whose tracks always resume where they were left
+    #[serde(default)]
+    pub resume_dirs: Vec<PathBuf>,
+    /// Directories scanned for the library, the music dir when empty
+    #[serde(default)]
+    pub library_dirs: Vec<PathBuf>,
+    /// Show play counts and ratings in track and playlist tables
+    #[serde(default)]
+    pub stats_columns: bool,
 }
 
 impl Config {
@@ -19,11 +51,37 @@ impl Config {
         )?)?)
     }
 
+    /// Crossfade duration limited to 12 seconds, no crossfade when it is not a number
+    pub fn crossfade_duration(&self) -> Duration {
+        if !self.crossfade.is_finite() {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(self.crossfade.clamp(0.0, 12.0))
+    }
+
+    /// Length from which tracks resume where they were left, ignored when it is not a number
+    pub fn resume_min_duration(&self) -> Option<Duration> {
+        self.resume_minutes
+            .filter(|minutes| minutes.is_finite())
+            .map(|minutes| Duration::from_secs_f64(minutes.max(0.0) * 60.0))
+    }
+
+    pub fn library_dirs(&self) -> Vec<PathBuf> {
+        if self.library_dirs.is_empty() {
+            vec![PathBuf::from(&self.music_dir)]
+        } else {
+            self.library_dirs.clone()
+        }
+    }
+
     pub fn load_playlists(&self) -> impl Iterator<Item = Playlist> {
         std::fs::read_dir(self.music_dir.clone())
             .into_iter()
             .flat_map(|read_dir| {
-                read_dir.filter_map(|entry| Some(Playlist::try_from(entry.ok()?.path()).ok()?))
+                read_dir
+                    .filter_map(|entry| Some(entry.ok()?.path()))
+                    .filter(|path| is_playlist(path))
+                    .filter_map(|path| Playlist::try_from(path).ok())
             })
     }
 }
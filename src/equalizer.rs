@@ -0,0 +1,424 @@
+use std::{
+    f64::consts::SQRT_2,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Style, Stylize},
+    widgets::{Cell, Row, Table, Widget},
+};
+use rodio::{Sample, Source, source::SeekError};
+
+use crate::{
+    CONFIG,
+    app::{AppState, quick_menu},
+    dsp::Biquad,
+    event::AppEvent,
+    menus::{Item, LinkedMenu, Menu, MenuFrame, NavigationResult, TableMenu},
+    trace_dbg,
+};
+
+pub const BANDS: usize = 10;
+/// Center frequencies of the bands in Hz
+pub const FREQUENCIES: [f64; BANDS] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+const MAX_GAIN: f32 = 12.0;
+const GAIN_STEP: f32 = 1.0;
+/// File in the data dir the last gains are saved to
+const EQUALIZER_FILE: &str = "equalizer";
+
+/// Gain of each band in dB
+pub type Gains = [f32; BANDS];
+
+/// Presets available without any config
+const PRESETS: [(&str, Gains); 5] = [
+    ("Flat", [0.0; BANDS]),
+    (
+        "Speaker",
+        [-12.0, -9.0, -6.0, -3.0, 0.0, 1.0, 3.0, 5.0, 4.0, 2.0],
+    ),
+    (
+        "Bass Boost",
+        [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+    ),
+    (
+        "Treble Boost",
+        [0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 4.0, 5.0, 6.0],
+    ),
+    (
+        "Vocal",
+        [-3.0, -3.0, -2.0, 0.0, 2.0, 4.0, 4.0, 2.0, 0.0, -1.0],
+    ),
+];
+
+/// Gains shared between the player and the sources it plays
+#[derive(Clone, Default)]
+pub struct EqualizerControl {
+    gains: Arc<Mutex<Gains>>,
+    /// Bumped whenever the gains change so sources know to update their filters
+    generation: Arc<AtomicU64>,
+    /// Data dir the gains are saved in
+    directory: PathBuf,
+}
+
+impl EqualizerControl {
+    /// Control starting from the last saved gains
+    pub fn load(directory: &Path) -> Self {
+        let control = Self {
+            directory: directory.to_path_buf(),
+            ..Self::default()
+        };
+        let saved = std::fs::read_to_string(directory.join(EQUALIZER_FILE))
+            .ok()
+            .and_then(|gains| {
+                gains
+                    .split_whitespace()
+                    .map(|gain| gain.parse::<f32>().ok())
+                    .collect::<Option<Vec<_>>>()
+            })
+            .and_then(|gains| Gains::try_from(gains).ok());
+        if let Some(gains) = saved {
+            control.store(gains);
+        }
+        control
+    }
+
+    pub fn gains(&self) -> Gains {
+        self.gains.lock().map(|gains| *gains).unwrap_or_default()
+    }
+
+    /// Sets and saves the gains
+    pub fn set_gains(&self, gains: Gains) {
+        self.store(gains);
+        if let Err(err) = self.save() {
+            trace_dbg!(err);
+        }
+    }
+
+    /// Raises or lowers a band by steps
+    pub fn adjust(&self, band: usize, steps: f32) {
+        let mut gains = self.gains();
+        if let Some(gain) = gains.get_mut(band) {
+            *gain += steps * GAIN_STEP;
+            self.set_gains(gains);
+        }
+    }
+
+    fn store(&self, gains: Gains) {
+        if let Ok(mut current) = self.gains.lock() {
+            *current = gains.map(|gain| gain.clamp(-MAX_GAIN, MAX_GAIN));
+        }
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(
+            self.directory.join(EQUALIZER_FILE),
+            self.gains().map(|gain| gain.to_string()).join(" "),
+        )
+    }
+}
+
+/// Filters for each band, bands above the nyquist frequency pass through
+fn band_filters(sample_rate: f64, gains: &Gains) -> [Biquad; BANDS] {
+    std::array::from_fn(|band| {
+        let (freq, gain) = (FREQUENCIES[band], gains[band] as f64);
+        if freq >= sample_rate / 2.0 {
+            Biquad::new(1.0, 0.0, 0.0, 0.0, 0.0)
+        } else if band == 0 {
+            Biquad::low_shelf(sample_rate, freq, SQRT_2 / 2.0, gain)
+        } else if band == BANDS - 1 {
+            Biquad::high_shelf(sample_rate, freq, SQRT_2 / 2.0, gain)
+        } else {
+            Biquad::peaking(sample_rate, freq, SQRT_2, gain)
+        }
+    })
+}
+
+/// Source filtered by the bands of an [`EqualizerControl`], following changes while playing
+pub struct Equalizer<S> {
+    inner: S,
+    control: EqualizerControl,
+    generation: u64,
+    sample_rate: u32,
+    /// Filters of each channel
+    filters: Vec<[Biquad; BANDS]>,
+    /// Are all the gains zero so filtering can be skipped
+    flat: bool,
+    /// Scale lowering the output by the largest boost so it doesn't clip
+    preamp: f64,
+    channel: usize,
+}
+
+impl<S: Source> Equalizer<S> {
+    pub fn new(inner: S, control: EqualizerControl) -> Self {
+        let mut equalizer = Self {
+            inner,
+            control,
+            generation: 0,
+            sample_rate: 0,
+            filters: vec![],
+            flat: true,
+            preamp: 1.0,
+            channel: 0,
+        };
+        equalizer.update();
+        equalizer
+    }
+
+    /// Updates the filters if the gains or the format changed
+    fn update(&mut self) {
+        let generation = self.control.generation.load(Ordering::Relaxed);
+        let channels = self.inner.channels().max(1) as usize;
+        let sample_rate = self.inner.sample_rate();
+        if generation == self.generation
+            && channels == self.filters.len()
+            && sample_rate == self.sample_rate
+        {
+            return;
+        }
+        let gains = self.control.gains();
+        let bands = band_filters(sample_rate as f64, &gains);
+        self.filters.resize(channels, bands);
+        for filters in &mut self.filters {
+            for (filter, band) in filters.iter_mut().zip(&bands) {
+                filter.set_coefficients(band);
+            }
+        }
+        self.flat = gains.iter().all(|gain| *gain == 0.0);
+        let boost = gains.iter().fold(0.0f32, |max, gain| max.max(*gain));
+        self.preamp = 10f64.powf(-boost as f64 / 20.0);
+        self.generation = generation;
+        self.sample_rate = sample_rate;
+    }
+}
+
+impl<S: Source> Iterator for Equalizer<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let sample = self.inner.next()?;
+        if self.channel == 0 {
+            self.update();
+        }
+        let channel = self.channel.min(self.filters.len() - 1);
+        self.channel = (self.channel + 1) % self.filters.len();
+        if self.flat {
+            return Some(sample);
+        }
+        let filtered = self.filters[channel]
+            .iter_mut()
+            .fold(sample as f64 * self.preamp, |sample, filter| {
+                filter.process(sample)
+            });
+        Some(filtered as Sample)
+    }
+}
+
+impl<S: Source> Source for Equalizer<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.inner.current_span_len()
+    }
+
+    fn channels(&self) -> rodio::ChannelCount {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> rodio::SampleRate {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.channel = 0;
+        Ok(())
+    }
+}
+
+/// Built in and user presets
+#[derive(Clone)]
+pub struct EqPreset {
+    name: String,
+    gains: Gains,
+}
+
+impl EqPreset {
+    /// Built in presets followed by the ones in the users config
+    pub fn all() -> Vec<Self> {
+        PRESETS
+            .iter()
+            .map(|(name, gains)| (name.to_string(), *gains))
+            .chain(CONFIG.eq_presets.clone())
+            .map(|(name, gains)| Self { name, gains })
+            .collect()
+    }
+}
+
+impl Item for EqPreset {}
+
+impl Into<AppEvent> for EqPreset {
+    fn into(self) -> AppEvent {
+        AppEvent::SetEqualizer(self.gains)
+    }
+}
+
+impl<'a> Into<Row<'a>> for EqPreset {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new(self.name)])
+    }
+}
+
+/// Bands of the equalizer, Enter starts editing with Left/Right picking a band and Up/Down
+/// changing its gain
+#[derive(Default)]
+pub struct EqualizerMenu {
+    control: Option<EqualizerControl>,
+    gains: Gains,
+    band: usize,
+    selected: bool,
+    editing: bool,
+}
+
+impl EqualizerMenu {
+    fn adjust(&mut self, steps: f32) {
+        if let Some(control) = self.control.as_ref() {
+            control.adjust(self.band, steps);
+            self.gains = control.gains();
+        }
+    }
+}
+
+impl Menu for EqualizerMenu {
+    fn up(&mut self) -> NavigationResult {
+        if self.editing {
+            self.adjust(1.0);
+            NavigationResult::Ok
+        } else if self.selected {
+            self.selected = false;
+            NavigationResult::Previous
+        } else {
+            self.selected = true;
+            NavigationResult::Ok
+        }
+    }
+
+    fn down(&mut self) -> NavigationResult {
+        if self.editing {
+            self.adjust(-1.0);
+            NavigationResult::Ok
+        } else if self.selected {
+            self.selected = false;
+            NavigationResult::Next
+        } else {
+            self.selected = true;
+            NavigationResult::Ok
+        }
+    }
+
+    fn left(&mut self) -> bool {
+        if self.selected {
+            self.band = self.band.saturating_sub(1);
+        }
+        self.selected
+    }
+
+    fn right(&mut self) -> bool {
+        if self.selected {
+            self.band = (self.band + 1).min(BANDS - 1);
+        }
+        self.selected
+    }
+
+    fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
+        self.editing = self.selected && !self.editing;
+        Ok(None)
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, _focused: bool) {
+        let highlight = |band: usize| match (band == self.band, self.editing, self.selected) {
+            (true, true, _) => Style::new().black().on_yellow(),
+            (true, false, true) => Style::new().yellow(),
+            _ => Style::new(),
+        };
+        Widget::render(
+            Table::new(
+                [Row::new(self.gains.iter().enumerate().map(
+                    |(band, gain)| Cell::new(format!("{gain:+}")).style(highlight(band)),
+                ))],
+                [Constraint::Fill(1); BANDS],
+            )
+            .header(Row::new(FREQUENCIES.iter().enumerate().map(
+                |(band, freq)| {
+                    Cell::new(if *freq >= 1000.0 {
+                        format!("{}k", freq / 1000.0)
+                    } else {
+                        format!("{freq}")
+                    })
+                    .style(highlight(band))
+                },
+            ))),
+            area,
+            buf,
+        );
+    }
+
+    fn constraint(&self) -> Constraint {
+        Constraint::Length(2)
+    }
+
+    fn tick(&mut self, app_state: &AppState) -> color_eyre::Result<()> {
+        let control = self
+            .control
+            .get_or_insert_with(|| app_state.player.equalizer().clone());
+        self.gains = control.gains();
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct EqualizerItem;
+
+impl Item for EqualizerItem {}
+
+impl EqualizerItem {
+    pub fn to_menu(self) -> TableMenu<EqualizerItem, [Constraint; 1]> {
+        TableMenu::new(vec![self], [Constraint::Fill(100)])
+    }
+}
+
+impl Into<AppEvent> for EqualizerItem {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(|| equalizer_menu()))
+    }
+}
+
+impl<'a> Into<Row<'a>> for EqualizerItem {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new("Equalizer")])
+    }
+}
+
+/// Band editor above the presets
+pub fn equalizer_menu() -> LinkedMenu {
+    LinkedMenu::new(Box::new(MenuFrame::new([
+        Box::new(EqualizerMenu::default()),
+        Box::new(
+            TableMenu::new(EqPreset::all(), [Constraint::Fill(100)])
+                .with_header(Row::new([Cell::new("Presets")])),
+        ),
+        quick_menu(),
+    ])))
+}
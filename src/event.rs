@@ -5,14 +5,18 @@ use ratatui::{
     crossterm::event::Event as CrosstermEvent,
     widgets::{Cell, Row},
 };
-use std::{fmt::Debug, sync::Arc, time::Duration};
+use std::{fmt::Debug, path::PathBuf, sync::Arc, time::Duration};
 use tokio::sync::mpsc;
 use tracing::trace;
 
 use crate::{
     device::Device,
+    equalizer::Gains,
     menus::{Item, LinkedMenu},
+    playlist::{Playlist, PlaylistEdit},
+    sleep::SleepPreset,
     track::Track,
+    watcher,
 };
 
 /// The frequency at which tick events are emitted.
@@ -36,6 +40,8 @@ pub enum Event {
     /// Use this event to emit custom events that are specific to your application.
     App(AppEvent),
     Blt(BltEvent),
+    /// Files changed in the music or library dirs
+    Fs(FsEvent),
 }
 
 /// Application events.
@@ -53,12 +59,108 @@ pub enum AppEvent {
     Pop,
     /// Add leaf linked menu
     Push(Arc<dyn Fn() -> LinkedMenu + Send + Sync>),
-    /// Play a playlist
-    Play(Vec<Track>),
+    /// Add leaf linked menu showing info
+    Info(Arc<dyn Fn() -> LinkedMenu + Send + Sync>),
+    /// Play tracks immediately
+    PlayNow(Vec<Track>),
+    /// Play tracks after the current track
+    PlayNext(Vec<Track>),
+    /// Play tracks after everything already queued
+    Enqueue(Vec<Track>),
     /// Resume track
     Resume,
     /// Pause track
     Pause,
+    /// Play the previous track
+    Previous,
+    /// Seek to the beginning of the track
+    Restart,
+    /// Play the queued track at an index sending the tracks before it to the history
+    QueueJump(usize),
+    /// Remove the queued track at an index
+    QueueRemove(usize),
+    /// Move the queued track at an index closer to playing
+    QueueMoveUp(usize),
+    /// Move the queued track at an index further from playing
+    QueueMoveDown(usize),
+    /// Remove all queued tracks
+    ClearQueue,
+    /// Shuffle the queue or restore its order
+    ToggleShuffle,
+    /// Switch to the next repeat mode
+    CycleRepeat,
+    /// Raise the volume
+    VolumeUp,
+    /// Lower the volume
+    VolumeDown,
+    /// Mute or unmute
+    ToggleMute,
+    /// Seek to a position in the track
+    Seek(Duration),
+    /// Seek forward by a duration
+    SeekForward(Duration),
+    /// Seek backward by a duration
+    SeekBackward(Duration),
+    /// Play faster
+    SpeedUp,
+    /// Play slower
+    SpeedDown,
+    /// Play at normal speed
+    ResetSpeed,
+    /// Keep the pitch when changing speed or let it follow
+    TogglePreservePitch,
+    /// Seek to the start of the next chapter
+    NextChapter,
+    /// Seek to the start of the current or previous chapter
+    PreviousChapter,
+    /// Play a track from the start of a chapter
+    ChapterJump(Track, Duration),
+    /// Ask for the name of a bookmark of the position in the current track
+    AddBookmark,
+    /// Bookmark a position in a track with a name
+    SaveBookmark(Track, Duration, String),
+    /// Play a track from a bookmarked position
+    BookmarkJump(Track, Duration),
+    /// Remove the bookmark of a track at an index
+    BookmarkRemove(Track, usize),
+    /// Mark the start or the end of a loop at the position in the current track
+    MarkLoop,
+    /// Stop looping
+    ClearLoop,
+    /// Switch to the next slowdown played while looping
+    CycleLoopSlowdown,
+    /// Scan the library dirs for changes
+    RescanLibrary,
+    /// Ask for a title and create a playlist of tracks
+    NewPlaylist(Vec<Track>),
+    /// Create a playlist with a title of tracks
+    CreatePlaylist(String, Vec<Track>),
+    /// Ask for a new title of the playlist at a path, starting from its title
+    RenamePlaylist(PathBuf, String),
+    /// Pick a playlist to add tracks to
+    AddToPlaylist(Vec<Track>),
+    /// Change the playlist at a path and write it back
+    EditPlaylist(PathBuf, PlaylistEdit),
+    /// Ask before deleting the playlist at a path
+    DeletePlaylist(PathBuf),
+    /// Delete the playlist at a path
+    ConfirmDeletePlaylist(PathBuf),
+    /// Write the playlist at a path as M3U
+    ExportPlaylist(PathBuf),
+    /// Rate the track at a path from zero to five stars
+    SetRating(PathBuf, u8),
+    /// Mark or unmark the track at a path as a favorite
+    ToggleFavorite(PathBuf),
+    /// Forget the play counts and rating of the track at a path
+    ResetStats(PathBuf),
+    /// Write the scrobble log as ListenBrainz JSON
+    ExportListens,
+    /// Pause after a while
+    SetSleepTimer(SleepPreset),
+    /// Stop the sleep timer
+    CancelSleepTimer,
+    /// Set the gain of every equalizer band
+    SetEqualizer(Gains),
     /// Connect with Device
     Connect(Device),
     /// Disconect Device
@@ -82,9 +184,57 @@ impl Debug for AppEvent {
                 Self::Quit => String::from("Quit"),
                 Self::Pop => String::from("Pop"),
                 Self::Push(_) => String::from("Push(..)"),
-                Self::Play(_) => String::from("Play(..)"),
+                Self::Info(_) => String::from("Info(..)"),
+                Self::PlayNow(_) => String::from("PlayNow(..)"),
+                Self::PlayNext(_) => String::from("PlayNext(..)"),
+                Self::Enqueue(_) => String::from("Enqueue(..)"),
                 Self::Resume => String::from("Resume"),
                 Self::Pause => String::from("Pause"),
+                Self::Previous => String::from("Previous"),
+                Self::Restart => String::from("Restart"),
+                Self::QueueJump(index) => format!("QueueJump({index})"),
+                Self::QueueRemove(index) => format!("QueueRemove({index})"),
+                Self::QueueMoveUp(index) => format!("QueueMoveUp({index})"),
+                Self::QueueMoveDown(index) => format!("QueueMoveDown({index})"),
+                Self::ClearQueue => String::from("ClearQueue"),
+                Self::ToggleShuffle => String::from("ToggleShuffle"),
+                Self::CycleRepeat => String::from("CycleRepeat"),
+                Self::VolumeUp => String::from("VolumeUp"),
+                Self::VolumeDown => String::from("VolumeDown"),
+                Self::ToggleMute => String::from("ToggleMute"),
+                Self::Seek(pos) => format!("Seek({pos:?})"),
+                Self::SeekForward(by) => format!("SeekForward({by:?})"),
+                Self::SeekBackward(by) => format!("SeekBackward({by:?})"),
+                Self::SpeedUp => String::from("SpeedUp"),
+                Self::SpeedDown => String::from("SpeedDown"),
+                Self::ResetSpeed => String::from("ResetSpeed"),
+                Self::TogglePreservePitch => String::from("TogglePreservePitch"),
+                Self::NextChapter => String::from("NextChapter"),
+                Self::PreviousChapter => String::from("PreviousChapter"),
+                Self::ChapterJump(_, pos) => format!("ChapterJump(.., {pos:?})"),
+                Self::AddBookmark => String::from("AddBookmark"),
+                Self::SaveBookmark(_, pos, name) => format!("SaveBookmark(.., {pos:?}, {name})"),
+                Self::BookmarkJump(_, pos) => format!("BookmarkJump(.., {pos:?})"),
+                Self::BookmarkRemove(_, index) => format!("BookmarkRemove(.., {index})"),
+                Self::MarkLoop => String::from("MarkLoop"),
+                Self::ClearLoop => String::from("ClearLoop"),
+                Self::CycleLoopSlowdown => String::from("CycleLoopSlowdown"),
+                Self::RescanLibrary => String::from("RescanLibrary"),
+                Self::NewPlaylist(_) => String::from("NewPlaylist(..)"),
+                Self::CreatePlaylist(title, _) => format!("CreatePlaylist({title}, ..)"),
+                Self::RenamePlaylist(path, _) => format!("RenamePlaylist({path:?}, ..)"),
+                Self::AddToPlaylist(_) => String::from("AddToPlaylist(..)"),
+                Self::EditPlaylist(path, edit) => format!("EditPlaylist({path:?}, {edit:?})"),
+                Self::DeletePlaylist(path) => format!("DeletePlaylist({path:?})"),
+                Self::ConfirmDeletePlaylist(path) => format!("ConfirmDeletePlaylist({path:?})"),
+                Self::ExportPlaylist(path) => format!("ExportPlaylist({path:?})"),
+                Self::SetRating(path, rating) => format!("SetRating({path:?}, {rating})"),
+                Self::ToggleFavorite(path) => format!("ToggleFavorite({path:?})"),
+                Self::ResetStats(path) => format!("ResetStats({path:?})"),
+                Self::ExportListens => String::from("ExportListens"),
+                Self::SetSleepTimer(preset) => format!("SetSleepTimer({preset:?})"),
+                Self::CancelSleepTimer => String::from("CancelSleepTimer"),
+                Self::SetEqualizer(gains) => format!("SetEqualizer({gains:?})"),
                 Self::Connect(device) => format!("Connect({})", device.address.to_string()),
                 Self::Trust(device) => format!("Trust({})", device.address.to_string()),
                 Self::Untrust(device) => format!("Untrust({})", device.address.to_string()),
@@ -104,9 +254,57 @@ impl Into<Row<'static>> for AppEvent {
             Self::Quit => "Quit",
             Self::Pop => "Pop",
             Self::Push(_) => "Push",
-            Self::Play(_) => "Play",
+            Self::Info(_) => "Info",
+            Self::PlayNow(_) => "Play",
+            Self::PlayNext(_) => "Play Next",
+            Self::Enqueue(_) => "Enqueue",
             Self::Resume => "Resume",
             Self::Pause => "Pause",
+            Self::Previous => "Previous",
+            Self::Restart => "Restart",
+            Self::QueueJump(_) => "Jump To",
+            Self::QueueRemove(_) => "Remove",
+            Self::QueueMoveUp(_) => "Move Up",
+            Self::QueueMoveDown(_) => "Move Down",
+            Self::ClearQueue => "Clear Queue",
+            Self::ToggleShuffle => "Toggle Shuffle",
+            Self::CycleRepeat => "Cycle Repeat",
+            Self::VolumeUp => "Volume Up",
+            Self::VolumeDown => "Volume Down",
+            Self::ToggleMute => "Toggle Mute",
+            Self::Seek(_) => "Seek",
+            Self::SeekForward(_) => "Seek Forward",
+            Self::SeekBackward(_) => "Seek Backward",
+            Self::SpeedUp => "Speed Up",
+            Self::SpeedDown => "Speed Down",
+            Self::ResetSpeed => "Normal Speed",
+            Self::TogglePreservePitch => "Toggle Preserve Pitch",
+            Self::NextChapter => "Next Chapter",
+            Self::PreviousChapter => "Previous Chapter",
+            Self::ChapterJump(..) => "Play From Chapter",
+            Self::AddBookmark => "Add Bookmark",
+            Self::SaveBookmark(..) => "Save Bookmark",
+            Self::BookmarkJump(..) => "Jump To",
+            Self::BookmarkRemove(..) => "Remove",
+            Self::MarkLoop => "Mark Loop Point",
+            Self::ClearLoop => "Clear Loop",
+            Self::CycleLoopSlowdown => "Loop Slowdown",
+            Self::RescanLibrary => "Rescan Library",
+            Self::NewPlaylist(_) => "New Playlist",
+            Self::CreatePlaylist(..) => "Create Playlist",
+            Self::RenamePlaylist(..) => "Rename Playlist",
+            Self::AddToPlaylist(_) => "Add To Playlist",
+            Self::EditPlaylist(_, edit) => edit.label(),
+            Self::DeletePlaylist(_) => "Delete Playlist",
+            Self::ConfirmDeletePlaylist(_) => "Delete",
+            Self::ExportPlaylist(_) => "Export M3U",
+            Self::SetRating(..) => "Rate",
+            Self::ToggleFavorite(_) => "Toggle Favorite",
+            Self::ResetStats(_) => "Reset Statistics",
+            Self::ExportListens => "Export ListenBrainz JSON",
+            Self::SetSleepTimer(_) => "Set Sleep Timer",
+            Self::CancelSleepTimer => "Cancel Sleep Timer",
+            Self::SetEqualizer(_) => "Set Equalizer",
             Self::Connect(_) => "Connect",
             Self::Trust(_) => "Trust",
             Self::Untrust(_) => "Untrust",
@@ -124,6 +322,23 @@ pub enum BltEvent {
     Remove(Address),
 }
 
+/// Changes to the library, playlists and statistics, tracks and playlists are already read
+#[derive(Clone, Debug)]
+pub enum FsEvent {
+    /// A scan of the library dirs finished
+    LibraryScanned,
+    /// Play counts or ratings changed
+    StatsChanged,
+    /// A track was added to or changed in the library dirs
+    TrackChanged(Track),
+    /// Tracks at or under a path were removed from the library dirs
+    TrackRemoved(PathBuf),
+    /// A playlist was added to or changed in the music dir
+    PlaylistChanged(Playlist),
+    /// A playlist was removed from the music dir
+    PlaylistRemoved(PathBuf),
+}
+
 /// Terminal event handler.
 #[derive(Debug)]
 pub struct EventHandler {
@@ -139,6 +354,8 @@ impl EventHandler {
         let (sender, receiver) = mpsc::unbounded_channel();
         let actor = EventTask::new(sender.clone());
         tokio::spawn(async { actor.run().await });
+        tokio::spawn(watcher::run(sender.clone()));
+        tokio::spawn(watcher::load_playlists(sender.clone()));
         Self { sender, receiver }
     }
 
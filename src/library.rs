@@ -0,0 +1,312 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, UNIX_EPOCH},
+};
+
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CONFIG, chapters::Chapter, logging::get_data_dir, replaygain::ReplayGain, trace_dbg,
+    track::Track,
+};
+
+/// File in the data dir the index of the library is saved to
+const INDEX_FILE: &str = "library.toml";
+/// Extensions of the files tags can be read from
+const EXTENSIONS: [&str; 5] = ["mp3", "flac", "m4a", "m4b", "mp4"];
+
+lazy_static! {
+    pub static ref LIBRARY: Library = Library::default();
+}
+
+/// Every track in the library dirs
+#[derive(Default)]
+pub struct Library {
+    tracks: RwLock<Arc<Vec<Track>>>,
+    /// The index is only changed and saved while this is locked
+    state: Mutex<State>,
+    scanning: AtomicBool,
+    /// Has a scan finished since it was last taken
+    scanned: AtomicBool,
+}
+
+impl Library {
+    /// Snapshot of the tracks ordered by path
+    pub fn tracks(&self) -> Arc<Vec<Track>> {
+        self.tracks
+            .read()
+            .map(|tracks| tracks.clone())
+            .unwrap_or_default()
+    }
+
+    /// Indexed track at a path
+    pub fn get(&self, path: &Path) -> Option<Track> {
+        let tracks = self.tracks();
+        let index = tracks
+            .binary_search_by(|track| track.path.as_path().cmp(path))
+            .ok()?;
+        Some(tracks[index].clone())
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scanning.load(Ordering::Relaxed)
+    }
+
+    /// Whether a scan finished since the last call
+    pub fn take_scanned(&self) -> bool {
+        self.scanned.swap(false, Ordering::Relaxed)
+    }
+
+    /// Loads the index then rescans the library dirs on another thread
+    ///
+    /// Only files modified since they were indexed have their tags read again, changes the
+    /// watcher applies during the scan are applied again over its result
+    pub fn scan(&'static self) {
+        if self.scanning.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        std::thread::spawn(move || {
+            let Some(index) = self.state.lock().ok().map(|mut state| {
+                let index = state.index.get_or_insert_with(Index::load).tracks.clone();
+                if self.tracks().is_empty() {
+                    self.set_tracks(index.iter().cloned().map(Track::from).collect());
+                }
+                state.pending = Some(vec![]);
+                index
+            }) else {
+                self.scanning.store(false, Ordering::Relaxed);
+                return;
+            };
+
+            let mut indexed = index
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect::<HashMap<_, _>>();
+            let mut files = vec![];
+            for dir in CONFIG.library_dirs() {
+                walk(&dir, &mut files);
+            }
+            files.sort();
+            files.dedup();
+
+            let entries = files
+                .into_iter()
+                .filter_map(|path| {
+                    let modified = modified(&path)?;
+                    match indexed.remove(&path) {
+                        Some(entry) if entry.modified == modified => Some(entry),
+                        _ => match Track::try_from(path) {
+                            Ok(track) => Some(IndexEntry::new(&track, modified)),
+                            Err(err) => {
+                                trace_dbg!(err);
+                                None
+                            }
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            if let Ok(mut state) = self.state.lock() {
+                let mut index = Index { tracks: entries };
+                for (changed, removed) in state.pending.take().unwrap_or_default() {
+                    index.merge(&changed, &removed);
+                }
+                self.commit(&index);
+                state.index = Some(index);
+            }
+            self.scanning.store(false, Ordering::Relaxed);
+            self.scanned.store(true, Ordering::Relaxed);
+        });
+    }
+
+    /// Applies changes the watcher saw to the tracks and the saved index
+    ///
+    /// Tracks at or under a removed path are dropped, changed tracks replace those at their path
+    pub fn apply(&self, changed: &[Track], removed: &[PathBuf]) {
+        let changed = changed
+            .iter()
+            .filter_map(|track| Some(IndexEntry::new(track, modified(&track.path)?)))
+            .collect::<Vec<_>>();
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if let Some(pending) = state.pending.as_mut() {
+            pending.push((changed.clone(), removed.to_vec()));
+        }
+        let index = state.index.get_or_insert_with(Index::load);
+        index.merge(&changed, removed);
+        self.commit(index);
+    }
+
+    /// Replaces the tracks with those of the index and saves it, only called with the state
+    /// locked
+    fn commit(&self, index: &Index) {
+        self.set_tracks(index.tracks.iter().cloned().map(Track::from).collect());
+        if let Err(err) = index.save() {
+            trace_dbg!(&err);
+        }
+    }
+
+    fn set_tracks(&self, tracks: Vec<Track>) {
+        if let Ok(mut current) = self.tracks.write() {
+            *current = Arc::new(tracks);
+        }
+    }
+}
+
+/// Collects the audio files under a dir without following links to dirs
+pub fn walk(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => walk(&path, files),
+            Ok(_) if is_audio(&path) => files.push(path),
+            _ => {}
+        }
+    }
+}
+
+pub fn is_audio(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Nanoseconds since the epoch the file was last modified
+pub fn modified(path: &Path) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64)
+}
+
+/// Index as last saved and the changes applied since a scan started
+#[derive(Default)]
+struct State {
+    /// Loaded on first use
+    index: Option<Index>,
+    /// Changed entries and removed paths applied during a scan
+    pending: Option<Vec<(Vec<IndexEntry>, Vec<PathBuf>)>>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Index {
+    #[serde(default)]
+    tracks: Vec<IndexEntry>,
+}
+
+impl Index {
+    fn load() -> Self {
+        std::fs::read_to_string(get_data_dir().join(INDEX_FILE))
+            .ok()
+            .and_then(|index| toml::from_str(&index).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> color_eyre::Result<()> {
+        let directory = get_data_dir();
+        std::fs::create_dir_all(&directory)?;
+        std::fs::write(directory.join(INDEX_FILE), toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Drops entries at or under a removed path and replaces those at the path of a changed one
+    fn merge(&mut self, changed: &[IndexEntry], removed: &[PathBuf]) {
+        self.tracks.retain(|entry| {
+            !removed
+                .iter()
+                .any(|removed| entry.path.starts_with(removed))
+                && !changed.iter().any(|changed| changed.path == entry.path)
+        });
+        self.tracks.extend(changed.iter().cloned());
+        self.tracks.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+}
+
+/// Tags of an indexed file
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexEntry {
+    path: PathBuf,
+    modified: u64,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    artist: String,
+    #[serde(default)]
+    album: String,
+    #[serde(default)]
+    genre: String,
+    year: Option<i32>,
+    track_number: Option<u16>,
+    /// Seconds
+    #[serde(default)]
+    duration: f64,
+    #[serde(default)]
+    replay_gain: ReplayGain,
+    #[serde(default)]
+    chapters: Vec<IndexChapter>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct IndexChapter {
+    title: String,
+    /// Seconds
+    start: f64,
+}
+
+impl IndexEntry {
+    fn new(track: &Track, modified: u64) -> Self {
+        Self {
+            path: track.path.clone(),
+            modified,
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            album: track.album.clone(),
+            genre: track.genre.clone(),
+            year: track.year,
+            track_number: track.track_number,
+            duration: track.total_duration.as_secs_f64(),
+            replay_gain: track.replay_gain,
+            chapters: track
+                .chapters
+                .iter()
+                .map(|chapter| IndexChapter {
+                    title: chapter.title.clone(),
+                    start: chapter.start.as_secs_f64(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<IndexEntry> for Track {
+    fn from(value: IndexEntry) -> Self {
+        Self {
+            path: value.path,
+            title: value.title,
+            artist: value.artist,
+            album: value.album,
+            genre: value.genre,
+            year: value.year,
+            track_number: value.track_number,
+            total_duration: Duration::from_secs_f64(value.duration.max(0.0)),
+            replay_gain: value.replay_gain,
+            chapters: value
+                .chapters
+                .into_iter()
+                .map(|chapter| Chapter {
+                    title: chapter.title,
+                    start: Duration::from_secs_f64(chapter.start.max(0.0)),
+                })
+                .collect(),
+            modified: Some(UNIX_EPOCH + Duration::from_nanos(value.modified)),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    collections::{HashMap, HashSet},
+    f64::consts::PI,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use color_eyre::eyre::eyre;
+use lazy_static::lazy_static;
+use rodio::{Decoder, Source};
+use serde::{Deserialize, Serialize};
+
+use crate::{dsp::Biquad, library, logging::get_data_dir, trace_dbg};
+
+/// File in the data dir computed gains are cached in
+const CACHE_FILE: &str = "loudness.toml";
+/// Loudness ReplayGain 2.0 normalizes to in LUFS
+const REFERENCE_LOUDNESS: f64 = -18.0;
+
+lazy_static! {
+    static ref CACHE: Mutex<LoudnessCache> = Mutex::new(LoudnessCache::load());
+    /// Tracks being analysed so each is only analysed once
+    static ref ANALYZING: Mutex<HashSet<PathBuf>> = Mutex::new(HashSet::new());
+}
+
+/// Gains in dB and sample peaks computed for tracks without ReplayGain tags
+#[derive(Serialize, Deserialize, Default)]
+struct LoudnessCache {
+    #[serde(default)]
+    gains: HashMap<PathBuf, f32>,
+    #[serde(default)]
+    peaks: HashMap<PathBuf, f32>,
+    /// Modification time of each file when it was analysed
+    #[serde(default)]
+    modified: HashMap<PathBuf, u64>,
+}
+
+impl LoudnessCache {
+    fn load() -> Self {
+        std::fs::read_to_string(get_data_dir().join(CACHE_FILE))
+            .ok()
+            .and_then(|cache| toml::from_str(&cache).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self) -> color_eyre::Result<()> {
+        let directory = get_data_dir();
+        std::fs::create_dir_all(&directory)?;
+        std::fs::write(directory.join(CACHE_FILE), toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Gain and peak computed by an earlier analysis of the track
+///
+/// Tracks changed since or analysed before peaks were measured have to be analysed again
+pub fn cached_gain(path: &Path) -> Option<(f32, f32)> {
+    let modified = library::modified(path)?;
+    let cache = CACHE.lock().ok()?;
+    if cache.modified.get(path) != Some(&modified) {
+        return None;
+    }
+    Some((*cache.gains.get(path)?, *cache.peaks.get(path)?))
+}
+
+/// Analyses the track on another thread caching its gain for the next time it is played
+pub fn analyze_in_background(path: PathBuf) {
+    let Ok(mut analyzing) = ANALYZING.lock() else {
+        return;
+    };
+    if !analyzing.insert(path.clone()) {
+        return;
+    }
+    drop(analyzing);
+    std::thread::spawn(move || {
+        let modified = library::modified(&path);
+        match integrated_loudness(&path) {
+            Ok((loudness, peak)) => {
+                if let Ok(mut cache) = CACHE.lock() {
+                    cache
+                        .gains
+                        .insert(path.clone(), (REFERENCE_LOUDNESS - loudness) as f32);
+                    cache.peaks.insert(path.clone(), peak);
+                    match modified {
+                        Some(modified) => cache.modified.insert(path.clone(), modified),
+                        None => cache.modified.remove(&path),
+                    };
+                    if let Err(err) = cache.save() {
+                        trace_dbg!(&err);
+                    }
+                }
+            }
+            Err(err) => {
+                trace_dbg!(&err);
+            }
+        }
+        if let Ok(mut analyzing) = ANALYZING.lock() {
+            analyzing.remove(&path);
+        }
+    });
+}
+
+/// EBU R128 integrated loudness of a track in LUFS and its sample peak as linear amplitude
+pub fn integrated_loudness(path: &Path) -> color_eyre::Result<(f64, f32)> {
+    let decoder = Decoder::try_from(File::open(path)?)?;
+    let channels = decoder.channels().max(1) as usize;
+    let sample_rate = decoder.sample_rate() as f64;
+    let mut filters = vec![k_weighting(sample_rate); channels];
+
+    // Mean square of each 100ms segment summed over the channels
+    let segment_len = (sample_rate / 10.0) as usize;
+    let mut segments = vec![];
+    let mut energy = 0.0;
+    let mut frames = 0;
+    let mut peak = 0f32;
+    for (index, sample) in decoder.enumerate() {
+        peak = peak.max(sample.abs());
+        let channel = index % channels;
+        let [shelf, high_pass] = &mut filters[channel];
+        let filtered = high_pass.process(shelf.process(sample as f64));
+        energy += filtered * filtered;
+        if channel == channels - 1 {
+            frames += 1;
+            if frames == segment_len {
+                segments.push(energy / segment_len as f64);
+                energy = 0.0;
+                frames = 0;
+            }
+        }
+    }
+
+    // Gating blocks are 400ms overlapping by 75%
+    let blocks = segments
+        .windows(4)
+        .map(|window| window.iter().sum::<f64>() / 4.0)
+        .filter(|block| loudness(*block) > -70.0)
+        .collect::<Vec<_>>();
+    if blocks.is_empty() {
+        return Err(eyre!("Track is silent"));
+    }
+    let relative_gate = loudness(mean(&blocks)) - 10.0;
+    let gated = blocks
+        .into_iter()
+        .filter(|block| loudness(*block) > relative_gate)
+        .collect::<Vec<_>>();
+    Ok((loudness(mean(&gated)), peak))
+}
+
+fn loudness(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len().max(1) as f64
+}
+
+/// Pre filter and RLB high pass of ITU-R BS.1770 for a sample rate
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let (f0, gain, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        (vh + vb * k / q + k * k) / a0,
+        2.0 * (k * k - vh) / a0,
+        (vh - vb * k / q + k * k) / a0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / q + k * k) / a0,
+    );
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        1.0,
+        -2.0,
+        1.0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / q + k * k) / a0,
+    );
+
+    [shelf, high_pass]
+}
@@ -8,15 +8,33 @@ use crate::app::App;
 
 pub mod app;
 mod audio_player;
+mod browse;
+mod chapters;
 pub mod config;
 pub mod device;
+mod dsp;
+mod equalizer;
 pub mod event;
 pub mod fatal;
+mod library;
 pub mod logging;
+mod loudness;
 pub mod menus;
+mod output;
 mod playlist;
+mod playlist_formats;
+mod prompt;
+mod replaygain;
+mod resume;
+mod rules;
+mod scrobble;
+mod search;
+mod sleep;
+mod stats;
+mod stretch;
 mod track;
 pub mod ui;
+mod watcher;
 
 pub type Error = Box<dyn std::error::Error>;
 pub type AppResult<T> = std::result::Result<T, Error>;
@@ -36,6 +54,7 @@ async fn main() -> color_eyre::Result<()> {
     if let Err(report) = App::new().await.run(&mut terminal).await {
         crate::fatal::FatalWidget(report).run(&mut terminal).await?;
     }
+    stats::STATS.flush();
     // check env var for reboot on exit
     ratatui::restore();
     Ok(())
@@ -1,4 +1,4 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{cmp::Ordering, fmt::Debug, sync::Arc};
 
 use color_eyre::eyre::OptionExt;
 use ratatui::{
@@ -17,8 +17,14 @@ use ratatui::{
 use crate::{
     CONFIG,
     app::{AppState, AudioWidgetMenu, quick_menu},
+    audio_player::QueueItem,
+    browse::LibraryItem,
     device::BluetoothItem,
+    equalizer::EqualizerItem,
     event::AppEvent,
+    playlist::{playlist_header, playlist_widths, refresh_playlists},
+    sleep::SleepItem,
+    track::Track,
 };
 
 pub enum NavigationResult {
@@ -31,6 +37,18 @@ pub enum NavigationResult {
 pub trait Menu {
     fn up(&mut self) -> NavigationResult;
     fn down(&mut self) -> NavigationResult;
+    /// Returns whether the menu used the key
+    fn left(&mut self) -> bool {
+        false
+    }
+    /// Returns whether the menu used the key
+    fn right(&mut self) -> bool {
+        false
+    }
+    /// Switches to the next order of the items, returns whether the menu can be sorted
+    fn sort(&mut self) -> bool {
+        false
+    }
     fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>>;
     fn render(&mut self, area: Rect, buf: &mut Buffer, focused: bool);
     fn constraint(&self) -> Constraint;
@@ -110,6 +128,27 @@ impl Menu for LinkedMenu {
         }
     }
 
+    fn left(&mut self) -> bool {
+        match self.next.as_mut() {
+            Some(next) => next.left(),
+            None => self.current.left(),
+        }
+    }
+
+    fn right(&mut self) -> bool {
+        match self.next.as_mut() {
+            Some(next) => next.right(),
+            None => self.current.right(),
+        }
+    }
+
+    fn sort(&mut self) -> bool {
+        match self.next.as_mut() {
+            Some(next) => next.sort(),
+            None => self.current.sort(),
+        }
+    }
+
     fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
         match self.next.as_mut() {
             Some(next) => next.enter(),
@@ -130,10 +169,12 @@ impl Menu for LinkedMenu {
         Constraint::Fill(100)
     }
 
+    /// Ticks every menu in the list so the ones below stay up to date to go back to
     fn tick(&mut self, app_state: &AppState) -> color_eyre::Result<()> {
+        self.current.tick(app_state)?;
         match self.next.as_mut() {
             Some(next) => next.tick(app_state),
-            None => self.current.tick(app_state),
+            None => Ok(()),
         }
     }
 }
@@ -202,6 +243,18 @@ where
         }
     }
 
+    fn left(&mut self) -> bool {
+        self.menus[self.selected].left()
+    }
+
+    fn right(&mut self) -> bool {
+        self.menus[self.selected].right()
+    }
+
+    fn sort(&mut self) -> bool {
+        self.menus[self.selected].sort()
+    }
+
     fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
         self.menus[self.selected].enter()
     }
@@ -243,6 +296,9 @@ where
     widths: C,
     header: Option<Row<'static>>,
     ticker: Option<fn(&mut Vec<T>, &AppState) -> color_eyre::Result<()>>,
+    /// Orders the items can be sorted in and the one they are in
+    sorts: Vec<fn(&T, &T) -> Ordering>,
+    sort: usize,
     state: TableState,
 }
 
@@ -258,10 +314,37 @@ where
             widths,
             header: None,
             ticker: None,
+            sorts: vec![],
+            sort: 0,
             state: TableState::default(),
         }
     }
 
+    /// Sorts the items in the first order, the others are cycled through with [`Menu::sort`]
+    pub fn with_sorts(mut self, sorts: Vec<fn(&T, &T) -> Ordering>) -> Self {
+        if let Some(sort) = sorts.first() {
+            self.items.sort_by(sort);
+        }
+        self.sorts = sorts;
+        self
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Replaces the items keeping the selection on the table and the order they are sorted in
+    pub fn set_items(&mut self, items: Vec<T>) {
+        self.items = items;
+        if let Some(sort) = self.sorts.get(self.sort) {
+            self.items.sort_by(sort);
+        }
+        if let Some(selected) = self.state.selected() {
+            self.state
+                .select((!self.items.is_empty()).then(|| selected.min(self.items.len() - 1)));
+        }
+    }
+
     pub fn with_header(mut self, header: Row<'static>) -> Self {
         self.header = Some(header);
         self
@@ -320,6 +403,15 @@ where
         }
     }
 
+    fn sort(&mut self) -> bool {
+        if self.sorts.is_empty() {
+            return false;
+        }
+        self.sort = (self.sort + 1) % self.sorts.len();
+        self.items.sort_by(self.sorts[self.sort]);
+        true
+    }
+
     fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
         Ok(match self.state.selected() {
             Some(index) => {
@@ -370,6 +462,30 @@ where
     }
 }
 
+/// Play, Enqueue, Play Next and Info actions for a selection of tracks
+pub fn action_menu(
+    title: String,
+    tracks: Vec<Track>,
+    info: Arc<dyn Fn() -> LinkedMenu + Send + Sync>,
+) -> LinkedMenu {
+    LinkedMenu::new(Box::new(MenuFrame::new([
+        Box::new(
+            TableMenu::new(
+                vec![
+                    AppEvent::PlayNow(tracks.clone()),
+                    AppEvent::Enqueue(tracks.clone()),
+                    AppEvent::PlayNext(tracks.clone()),
+                    AppEvent::AddToPlaylist(tracks),
+                    AppEvent::Info(info),
+                ],
+                [Constraint::Fill(100)],
+            )
+            .with_header(Row::new([Cell::new(title)])),
+        ),
+        quick_menu(),
+    ])))
+}
+
 pub fn make_test_menu() -> LinkedMenu {
     LinkedMenu::new(Box::new(MenuFrame::new([
         Box::new(TextMenu(
@@ -380,7 +496,11 @@ Copyright (c) Rayla-rs <wassrayla@gmail.com>
             )
             .centered(),
         )),
+        Box::new(LibraryItem.to_menu()),
         Box::new(PlaylistItem.to_menu()),
+        Box::new(QueueItem.to_menu()),
+        Box::new(EqualizerItem.to_menu()),
+        Box::new(SleepItem.to_menu()),
         Box::new(BluetoothItem.to_menu()),
         quick_menu(),
         Box::new(AudioWidgetMenu::default()),
@@ -413,20 +533,14 @@ impl Item for PlaylistItem {}
 pub fn playlist_collection_menu() -> LinkedMenu {
     LinkedMenu::new(Box::new(MenuFrame::new([
         Box::new(
-            TableMenu::new(
-                CONFIG.load_playlists().collect(),
-                [
-                    Constraint::Min(5),
-                    Constraint::Length(6),
-                    Constraint::Length(8),
-                ],
-            )
-            .with_header(Row::new([
-                Cell::new("Title"),
-                Cell::new("Tracks"),
-                Cell::new("Duration"),
-            ])),
+            TableMenu::new(CONFIG.load_playlists().collect(), playlist_widths())
+                .with_header(playlist_header())
+                .with_ticker(refresh_playlists),
         ),
+        Box::new(TableMenu::new(
+            vec![AppEvent::NewPlaylist(vec![])],
+            [Constraint::Fill(100)],
+        )),
         quick_menu(),
     ])))
 }
@@ -0,0 +1,237 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use rodio::{
+    ChannelCount, OutputStream, OutputStreamBuilder, SampleRate,
+    mixer::{self, Mixer, MixerSource},
+};
+use serde::Deserialize;
+
+use crate::trace_dbg;
+
+const CHANNELS: ChannelCount = 2;
+const SAMPLE_RATE: SampleRate = 44_100;
+/// Length of audio headless outputs consume at a time
+const PERIOD: Duration = Duration::from_millis(10);
+
+/// Destination the audio player's sink plays into
+pub trait Output {
+    fn mixer(&self) -> &Mixer;
+
+    fn name(&self) -> &'static str;
+}
+
+/// Output selected in the users config
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputConfig {
+    #[default]
+    Device,
+    Null,
+    Wav(PathBuf),
+}
+
+/// Opens the configured output falling back to a [`NullOutput`]
+///
+/// Returns a warning for the user when falling back
+pub fn open(config: &OutputConfig) -> (Box<dyn Output>, Option<String>) {
+    let output: color_eyre::Result<Box<dyn Output>> = match config {
+        OutputConfig::Device => DeviceOutput::open().map(|output| Box::new(output) as _),
+        OutputConfig::Null => Ok(Box::new(NullOutput::new())),
+        OutputConfig::Wav(path) => WavOutput::create(path).map(|output| Box::new(output) as _),
+    };
+    match output {
+        Ok(output) => (output, None),
+        Err(err) => {
+            trace_dbg!(&err);
+            (
+                Box::new(NullOutput::new()),
+                Some(format!("No audio output, playing silently: {err}")),
+            )
+        }
+    }
+}
+
+/// Default audio device
+pub struct DeviceOutput(OutputStream);
+
+impl DeviceOutput {
+    pub fn open() -> color_eyre::Result<Self> {
+        Ok(Self(OutputStreamBuilder::open_default_stream()?))
+    }
+}
+
+impl Output for DeviceOutput {
+    fn mixer(&self) -> &Mixer {
+        self.0.mixer()
+    }
+
+    fn name(&self) -> &'static str {
+        "device"
+    }
+}
+
+/// Discards audio in real time
+pub struct NullOutput {
+    mixer: Mixer,
+    _drain: Drain,
+}
+
+impl NullOutput {
+    pub fn new() -> Self {
+        let (mixer, source) = mixer::mixer(CHANNELS, SAMPLE_RATE);
+        Self {
+            mixer,
+            _drain: Drain::spawn(source, |_| Ok(())),
+        }
+    }
+}
+
+impl Output for NullOutput {
+    fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    fn name(&self) -> &'static str {
+        "null"
+    }
+}
+
+/// Records audio in real time to a 16 bit PCM wav file
+pub struct WavOutput {
+    mixer: Mixer,
+    _drain: Drain,
+}
+
+impl WavOutput {
+    pub fn create(path: &Path) -> color_eyre::Result<Self> {
+        let mut writer = WavWriter::create(path)?;
+        let (mixer, source) = mixer::mixer(CHANNELS, SAMPLE_RATE);
+        Ok(Self {
+            mixer,
+            _drain: Drain::spawn(source, move |samples| writer.write(samples)),
+        })
+    }
+}
+
+impl Output for WavOutput {
+    fn mixer(&self) -> &Mixer {
+        &self.mixer
+    }
+
+    fn name(&self) -> &'static str {
+        "wav"
+    }
+}
+
+/// Thread pulling samples out of a mixer at the rate a device would
+struct Drain {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drain {
+    fn spawn<F>(mut source: MixerSource, mut consume: F) -> Self
+    where
+        F: FnMut(&[f32]) -> std::io::Result<()> + Send + 'static,
+    {
+        let running = Arc::new(AtomicBool::new(true));
+        let handle = std::thread::spawn({
+            let running = running.clone();
+            move || {
+                let len =
+                    (SAMPLE_RATE as usize * CHANNELS as usize) * PERIOD.as_millis() as usize / 1000;
+                let mut buffer = Vec::with_capacity(len);
+                let mut deadline = Instant::now();
+                while running.load(Ordering::Relaxed) {
+                    buffer.clear();
+                    buffer.extend((0..len).map(|_| source.next().unwrap_or_default()));
+                    if let Err(err) = consume(&buffer) {
+                        trace_dbg!(err);
+                        break;
+                    }
+                    deadline += PERIOD;
+                    std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+                }
+            }
+        });
+        Self {
+            running,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Drain {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Minimal wav writer, the header is rewritten with the final sizes on drop
+struct WavWriter {
+    file: BufWriter<File>,
+    samples: u32,
+}
+
+impl WavWriter {
+    fn create(path: &Path) -> std::io::Result<Self> {
+        let mut writer = Self {
+            file: BufWriter::new(File::create(path)?),
+            samples: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> std::io::Result<()> {
+        let block_align = CHANNELS * 2;
+        let data_len = self.samples.saturating_mul(2);
+        self.file.seek(SeekFrom::Start(0))?;
+        self.file.write_all(b"RIFF")?;
+        self.file
+            .write_all(&data_len.saturating_add(36).to_le_bytes())?;
+        self.file.write_all(b"WAVEfmt ")?;
+        self.file.write_all(&16u32.to_le_bytes())?;
+        self.file.write_all(&1u16.to_le_bytes())?;
+        self.file.write_all(&CHANNELS.to_le_bytes())?;
+        self.file.write_all(&SAMPLE_RATE.to_le_bytes())?;
+        self.file
+            .write_all(&(SAMPLE_RATE * block_align as u32).to_le_bytes())?;
+        self.file.write_all(&block_align.to_le_bytes())?;
+        self.file.write_all(&16u16.to_le_bytes())?;
+        self.file.write_all(b"data")?;
+        self.file.write_all(&data_len.to_le_bytes())?;
+        self.file.seek(SeekFrom::End(0))?;
+        Ok(())
+    }
+
+    fn write(&mut self, samples: &[f32]) -> std::io::Result<()> {
+        for sample in samples {
+            let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.samples = self.samples.saturating_add(samples.len() as u32);
+        Ok(())
+    }
+}
+
+impl Drop for WavWriter {
+    fn drop(&mut self) {
+        if let Err(err) = self.write_header().and_then(|_| self.file.flush()) {
+            trace_dbg!(err);
+        }
+    }
+}
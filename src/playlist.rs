@@ -1,20 +1,35 @@
-use std::{fmt::Display, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use hhmmss::Hhmmss;
 use ratatui::{
-    layout::Constraint,
-    text::Text,
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::{Line, Text},
     widgets::{Cell, Row},
 };
 use rodio::Source;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    app::quick_menu,
-    event::AppEvent,
-    menus::{Item, LinkedMenu, MenuFrame, TableMenu, TextMenu},
+    CONFIG,
+    app::{AppState, quick_menu},
+    event::{AppEvent, FsEvent},
+    library::LIBRARY,
+    menus::{
+        Item, LinkedMenu, Menu, MenuFrame, NavigationResult, TableMenu, TextMenu, action_menu,
+    },
+    playlist_formats::{self, EntryInfo},
+    rules::Rules,
+    stats::{self, STATS},
     trace_dbg,
-    track::Track,
+    track::{Track, track_header, track_info_menu, track_widths},
 };
 
 #[derive(Debug, Clone)]
@@ -22,6 +37,12 @@ pub struct Playlist {
     pub title: String,
     pub tracks: Vec<Track>,
     pub path: PathBuf,
+    /// Index in the file of each track's entry, empty for smart playlists
+    pub entries: Vec<usize>,
+    /// Rules the tracks of a smart playlist come from
+    pub rules: Option<Rules>,
+    /// Entries that did not load and why
+    pub unresolved: Vec<String>,
 }
 
 impl Display for Playlist {
@@ -36,6 +57,67 @@ impl Playlist {
             .iter()
             .fold(Duration::default(), |acc, elem| acc + elem.total_duration)
     }
+
+    /// Applies the track changes the watcher saw, returns whether the playlist changed
+    ///
+    /// Smart playlists are evaluated again on any change to the library or the statistics, removed
+    /// tracks of other playlists keep their entries in the file and are listed as unresolved
+    fn refresh(&mut self, events: &[FsEvent]) -> bool {
+        if let Some(rules) = &self.rules {
+            if !events.iter().any(|event| {
+                matches!(
+                    event,
+                    FsEvent::TrackChanged(_)
+                        | FsEvent::TrackRemoved(_)
+                        | FsEvent::LibraryScanned
+                        | FsEvent::StatsChanged
+                )
+            }) {
+                return false;
+            }
+            self.tracks = rules.evaluate(&LIBRARY.tracks(), &STATS);
+            return true;
+        }
+        let mut changed = false;
+        for event in events {
+            changed |= self.refresh_track(event);
+        }
+        changed
+    }
+
+    /// Applies a track change to a playlist without rules, returns whether a track was affected
+    fn refresh_track(&mut self, event: &FsEvent) -> bool {
+        match event {
+            FsEvent::TrackChanged(changed) => {
+                let mut affected = false;
+                for track in self
+                    .tracks
+                    .iter_mut()
+                    .filter(|track| track.path == changed.path)
+                {
+                    *track = changed.clone();
+                    affected = true;
+                }
+                affected
+            }
+            FsEvent::TrackRemoved(path) => {
+                let before = self.tracks.len();
+                let mut index = 0;
+                while index < self.tracks.len() {
+                    if self.tracks[index].path.starts_with(path) {
+                        let track = self.tracks.remove(index);
+                        self.entries.remove(index);
+                        self.unresolved
+                            .push(format!("{}: file not found", track.path.display()));
+                    } else {
+                        index += 1;
+                    }
+                }
+                before != self.tracks.len()
+            }
+            _ => false,
+        }
+    }
 }
 
 impl Item for Playlist {}
@@ -47,44 +129,253 @@ impl<'a> Into<Row<'a>> for Playlist {
             self.tracks.len().to_string(),
             self.get_duration().hhmmss(),
         ]
-        .iter()
-        .map(|elem| Cell::from(Text::from(format!("{elem}"))))
+        .into_iter()
+        .chain(stats::tracks_cells(&self.tracks))
+        .map(|elem| Cell::from(Text::from(elem)))
         .collect()
     }
 }
 
+/// Widths of the columns of playlist rows
+pub fn playlist_widths() -> Vec<Constraint> {
+    [
+        Constraint::Min(5),
+        Constraint::Length(6),
+        Constraint::Length(8),
+    ]
+    .into_iter()
+    .chain(stats::widths())
+    .collect()
+}
+
+/// Header of playlist rows
+pub fn playlist_header() -> Row<'static> {
+    Row::new(
+        ["Title", "Tracks", "Duration"]
+            .into_iter()
+            .map(Cell::new)
+            .chain(stats::header()),
+    )
+}
+
+/// Title, tracks and path of a playlist above its entries
+fn info(playlist: &Playlist) -> TextMenu {
+    TextMenu(Text::from(
+        vec![
+            Line::from(format!("Title: {}", playlist.title)),
+            Line::from(format!("Tracks: {}", playlist.tracks.len())),
+            Line::from(format!("Duration: {}", playlist.get_duration().hhmmss())),
+            Line::from(format!("Path: {}", playlist.path.display())),
+        ]
+        .into_iter()
+        .chain(
+            playlist
+                .rules
+                .is_some()
+                .then(|| Line::from("Smart playlist, tracks chosen by its rules")),
+        )
+        .chain(
+            (!playlist.unresolved.is_empty())
+                .then(|| Line::from(format!("Unresolved: {}", playlist.unresolved.len()))),
+        )
+        .chain(
+            playlist
+                .unresolved
+                .iter()
+                .map(|entry| Line::from(format!("  {entry}")).yellow()),
+        )
+        .collect::<Vec<_>>(),
+    ))
+}
+
+/// Track of a playlist and the index of its entry in the file
+///
+/// Tracks of smart playlists have no entry and can not be edited
+#[derive(Clone)]
+pub struct PlaylistEntry {
+    pub path: PathBuf,
+    pub index: Option<usize>,
+    pub track: Track,
+}
+
+impl PlaylistEntry {
+    fn entries(playlist: &Playlist) -> Vec<Self> {
+        playlist
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(position, track)| Self {
+                path: playlist.path.clone(),
+                index: playlist.entries.get(position).copied(),
+                track: track.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Item for PlaylistEntry {}
+
+impl<'a> Into<Row<'a>> for PlaylistEntry {
+    fn into(self) -> Row<'a> {
+        self.track.into()
+    }
+}
+
+impl Into<AppEvent> for PlaylistEntry {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(move || {
+            let track = self.track.clone();
+            let mut actions = vec![
+                AppEvent::PlayNow(vec![track.clone()]),
+                AppEvent::Enqueue(vec![track.clone()]),
+            ];
+            if let Some(index) = self.index {
+                actions.extend(
+                    [
+                        PlaylistEdit::MoveUp(index),
+                        PlaylistEdit::MoveDown(index),
+                        PlaylistEdit::Remove(index),
+                    ]
+                    .map(|edit| AppEvent::EditPlaylist(self.path.clone(), edit)),
+                );
+            }
+            actions.push(AppEvent::Info(Arc::new(move || {
+                track_info_menu(track.clone())
+            })));
+            LinkedMenu::new(Box::new(MenuFrame::new([
+                Box::new(
+                    TableMenu::new(actions, [Constraint::Fill(100)])
+                        .with_header(Row::new([Cell::new(self.track.title.clone())])),
+                ),
+                quick_menu(),
+            ])))
+        }))
+    }
+}
+
+/// Info and entries of a playlist kept up to date as the playlist is edited
+struct PlaylistView {
+    playlist: Playlist,
+    info: TextMenu,
+    entries: TableMenu<PlaylistEntry, Vec<Constraint>>,
+}
+
+impl PlaylistView {
+    fn new(playlist: Playlist) -> Self {
+        Self {
+            info: info(&playlist),
+            entries: TableMenu::new(PlaylistEntry::entries(&playlist), track_widths())
+                .with_header(track_header("Title")),
+            playlist,
+        }
+    }
+
+    /// Rebuilds the info and the entries after the playlist changed
+    fn update(&mut self) {
+        self.info = info(&self.playlist);
+        self.entries
+            .set_items(PlaylistEntry::entries(&self.playlist));
+    }
+}
+
+impl Menu for PlaylistView {
+    fn up(&mut self) -> NavigationResult {
+        self.entries.up()
+    }
+
+    fn down(&mut self) -> NavigationResult {
+        self.entries.down()
+    }
+
+    fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
+        self.entries.enter()
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, focused: bool) {
+        let [info, entries] =
+            Layout::vertical([self.info.constraint(), Constraint::Fill(100)]).areas(area);
+        self.info.render(info, buf, false);
+        self.entries.render(entries, buf, focused);
+    }
+
+    fn constraint(&self) -> Constraint {
+        Constraint::Fill(100)
+    }
+
+    fn tick(&mut self, app_state: &AppState) -> color_eyre::Result<()> {
+        let mut changed = false;
+        for event in &app_state.fs_events {
+            match event {
+                FsEvent::PlaylistChanged(edited) if edited.path == self.playlist.path => {
+                    self.playlist = edited.clone();
+                    changed = true;
+                }
+                FsEvent::PlaylistRemoved(path) if path == &self.playlist.path => {
+                    self.playlist.tracks.clear();
+                    self.playlist.entries.clear();
+                    self.playlist.title = format!("{} (deleted)", self.playlist.title);
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        changed |= self.playlist.refresh(&app_state.fs_events);
+        if changed {
+            self.update();
+        }
+        Ok(())
+    }
+}
+
 pub fn playlist_menu(playlist: Playlist) -> LinkedMenu {
+    let actions = vec![
+        AppEvent::RenamePlaylist(playlist.path.clone(), playlist.title.clone()),
+        AppEvent::ExportPlaylist(playlist.path.clone()),
+        AppEvent::DeletePlaylist(playlist.path.clone()),
+    ];
     LinkedMenu::new(Box::new(MenuFrame::new([
-        Box::new(
-            TableMenu::new(
-                vec![AppEvent::Play(playlist.tracks.clone())],
-                [Constraint::Fill(100)],
-            )
-            .with_header(Row::new([Cell::new(playlist.title.clone())])),
-        ),
-        Box::new(
-            TableMenu::new(
-                playlist.tracks,
-                [
-                    Constraint::Min(5),
-                    Constraint::Length(6),
-                    Constraint::Length(8),
-                ],
-            )
-            .with_header(Row::new([
-                Cell::new("Title"),
-                Cell::new("Artist"),
-                Cell::new("Duration"),
-            ])),
-        ),
+        Box::new(PlaylistView::new(playlist)),
+        Box::new(TableMenu::new(actions, [Constraint::Fill(100)])),
         quick_menu(),
     ])))
 }
 
-/// Incomplete here for testing only
+/// Brings the playlists and their tracks up to date with the changes the watcher saw
+pub fn refresh_playlists(
+    playlists: &mut Vec<Playlist>,
+    app_state: &AppState,
+) -> color_eyre::Result<()> {
+    for event in &app_state.fs_events {
+        match event {
+            FsEvent::PlaylistChanged(changed) => {
+                match playlists
+                    .iter_mut()
+                    .find(|playlist| playlist.path == changed.path)
+                {
+                    Some(playlist) => *playlist = changed.clone(),
+                    None => playlists.push(changed.clone()),
+                }
+            }
+            FsEvent::PlaylistRemoved(path) => playlists.retain(|playlist| &playlist.path != path),
+            _ => {}
+        }
+    }
+    for playlist in playlists.iter_mut() {
+        playlist.refresh(&app_state.fs_events);
+    }
+    Ok(())
+}
+
 impl Into<AppEvent> for Playlist {
     fn into(self) -> AppEvent {
-        AppEvent::Push(Arc::new(move || playlist_menu(self.clone())))
+        AppEvent::Push(Arc::new(move || {
+            let playlist = self.clone();
+            action_menu(
+                self.title.clone(),
+                self.tracks.clone(),
+                Arc::new(move || playlist_menu(playlist.clone())),
+            )
+        }))
     }
 }
 
@@ -98,47 +389,380 @@ impl TryFrom<PathBuf> for Playlist {
 impl TryFrom<PlyData> for Playlist {
     type Error = crate::Error;
     fn try_from(value: PlyData) -> Result<Self, Self::Error> {
+        let title = if value.title.is_empty() {
+            value
+                .path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        } else {
+            value.title
+        };
+        if let Some(rules) = value.rules {
+            return Ok(Self {
+                title,
+                tracks: rules.evaluate(&LIBRARY.tracks(), &STATS),
+                path: value.path,
+                entries: vec![],
+                rules: Some(rules),
+                unresolved: vec![],
+            });
+        }
+
+        let dir = value.path.parent().unwrap_or(Path::new("/"));
+        let mut tracks = vec![];
+        let mut entries = vec![];
+        let mut unresolved = vec![];
+        for (index, entry) in value.tracks.into_iter().enumerate() {
+            // Indexed tracks save reading the tags again
+            match playlist_formats::resolve(&entry, dir).and_then(|path| match LIBRARY.get(&path) {
+                Some(track) => Ok(track),
+                None => Track::try_from(path).map_err(|err| err.to_string()),
+            }) {
+                Ok(track) => {
+                    tracks.push(track);
+                    entries.push(index);
+                }
+                Err(reason) => {
+                    trace_dbg!(&reason);
+                    unresolved.push(format!("{entry}: {reason}"));
+                }
+            }
+        }
         Ok(Self {
-            title: value.title,
-            tracks: value
-                .tracks
-                .iter()
-                .filter_map(|track| {
-                    let mut path = value.path.clone();
-                    let _ = path.pop();
-                    // path.push(format!("\\{track}"));
-                    path.push(PathBuf::from(track)); // Bug here fix me pls master :3
-                    match Track::try_from(path) {
-                        Ok(track) => Some(track),
-                        Err(err) => {
-                            trace_dbg!(err);
-                            None
-                        }
-                    }
-                })
-                .collect::<Vec<_>>(),
+            title,
+            tracks,
             path: value.path,
+            entries,
+            rules: None,
+            unresolved,
         })
     }
 }
 
 /// Playlist data file struct for deserializing
-#[derive(Deserialize, Debug)]
+///
+/// TOML playlists deserialize into it, M3U, PLS and XSPF playlists are parsed into it.
+/// The tracks of TOML playlists with rules come from the library instead of the entries.
+#[derive(Serialize, Deserialize, Debug)]
 struct PlyData {
     #[serde(default)]
     title: String,
     #[serde(default)]
     tracks: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rules: Option<Rules>,
+    /// Titles and lengths of the entries of M3U and PLS files, kept when they are written back
+    #[serde(skip)]
+    info: Vec<Option<EntryInfo>>,
     #[serde(skip)]
     path: PathBuf,
+    /// Text of a TOML file, edited in place so its comments and other keys stay
+    #[serde(skip)]
+    source: String,
 }
 
 impl TryFrom<PathBuf> for PlyData {
     type Error = crate::Error;
     fn try_from(value: PathBuf) -> Result<Self, Self::Error> {
+        let extension = value
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        // M3U files are not always UTF-8
+        let text = String::from_utf8_lossy(&std::fs::read(&value)?).into_owned();
+        let (title, entries) = match extension.as_str() {
+            "toml" => {
+                let data: Self = toml::from_str(&text)?;
+                return Ok(Self {
+                    path: value,
+                    source: text,
+                    ..data
+                });
+            }
+            "m3u" | "m3u8" => playlist_formats::parse_m3u(&text),
+            "pls" => (String::new(), playlist_formats::parse_pls(&text)),
+            "xspf" => {
+                let (title, tracks) = playlist_formats::parse_xspf(&text);
+                (
+                    title,
+                    tracks.into_iter().map(|entry| (entry, None)).collect(),
+                )
+            }
+            _ => return Err(format!("{} is not a playlist", value.display()).into()),
+        };
+        let (tracks, info) = entries.into_iter().unzip();
         Ok(Self {
-            path: value.clone(),
-            ..toml::from_str(&std::fs::read_to_string(value)?)?
+            title,
+            tracks,
+            rules: None,
+            info,
+            path: value,
+            source: String::new(),
+        })
+    }
+}
+
+impl PlyData {
+    /// Writes the playlist back in the format of its file
+    ///
+    /// The file is replaced by renaming a written copy so it is never left half written
+    fn save(&self) -> crate::AppResult<()> {
+        let extension = self
+            .path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let entries = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| (entry.clone(), self.info.get(index).cloned().flatten()))
+            .collect::<Vec<_>>();
+        let text = match extension.as_str() {
+            "m3u" | "m3u8" => playlist_formats::write_m3u(&self.title, &entries),
+            "pls" => playlist_formats::write_pls(&entries),
+            "xspf" => playlist_formats::write_xspf(&self.title, &self.tracks),
+            _ => {
+                let mut document = self.source.parse::<toml_edit::DocumentMut>()?;
+                set_toml(&mut document, "title", self.title.as_str().into());
+                set_toml(
+                    &mut document,
+                    "tracks",
+                    self.tracks
+                        .iter()
+                        .map(String::as_str)
+                        .collect::<toml_edit::Array>()
+                        .into(),
+                );
+                document.to_string()
+            }
+        };
+        write_atomic(&self.path, &text)
+    }
+}
+
+/// Sets a key of a TOML document keeping the comments around its old value
+fn set_toml(document: &mut toml_edit::DocumentMut, key: &str, mut value: toml_edit::Value) {
+    match document
+        .get_mut(key)
+        .and_then(toml_edit::Item::as_value_mut)
+    {
+        Some(old) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        None => document[key] = toml_edit::Item::Value(value),
+    }
+}
+
+/// Writes a file by renaming a written copy over it, the copy is removed if that fails
+fn write_atomic(path: &Path, text: &str) -> crate::AppResult<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("{} is not a file", path.display()))?;
+    let temporary = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    let result = std::fs::File::create(&temporary)
+        .and_then(|mut file| {
+            file.write_all(text.as_bytes())?;
+            file.sync_all()
         })
+        .and_then(|()| std::fs::rename(&temporary, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temporary);
+    }
+    Ok(result?)
+}
+
+/// Change to the entries or the title of a playlist file
+#[derive(Debug, Clone)]
+pub enum PlaylistEdit {
+    Rename(String),
+    Add(Vec<Track>),
+    Remove(usize),
+    MoveUp(usize),
+    MoveDown(usize),
+}
+
+impl PlaylistEdit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Rename(_) => "Rename",
+            Self::Add(_) => "Add",
+            Self::Remove(_) => "Remove From Playlist",
+            Self::MoveUp(_) => "Move Up",
+            Self::MoveDown(_) => "Move Down",
+        }
+    }
+}
+
+/// Applies an edit to a playlist file and loads the result
+///
+/// Added tracks are written relative to the playlist's dir when they share more than the root
+pub fn edit(path: &Path, edit: PlaylistEdit) -> crate::AppResult<Playlist> {
+    let mut data = PlyData::try_from(path.to_path_buf())?;
+    let len = data.tracks.len();
+    data.info.resize(len, None);
+    match edit {
+        PlaylistEdit::Rename(_)
+            if path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("pls")) =>
+        {
+            return Err("PLS playlists have no title, rename the file instead".into());
+        }
+        PlaylistEdit::Rename(title) => data.title = title,
+        PlaylistEdit::Add(tracks) => {
+            let dir = path.parent().unwrap_or(Path::new("/"));
+            for track in tracks {
+                data.tracks
+                    .push(playlist_formats::relative_entry(&track.path, dir));
+                data.info.push(Some(entry_info(&track)));
+            }
+        }
+        PlaylistEdit::Remove(index) if index < len => {
+            data.tracks.remove(index);
+            data.info.remove(index);
+        }
+        PlaylistEdit::MoveUp(index) if index > 0 && index < len => {
+            data.tracks.swap(index, index - 1);
+            data.info.swap(index, index - 1);
+        }
+        PlaylistEdit::MoveDown(index) if index + 1 < len => {
+            data.tracks.swap(index, index + 1);
+            data.info.swap(index, index + 1);
+        }
+        _ => {}
+    }
+    data.save()?;
+    Playlist::try_from(data)
+}
+
+/// `#EXTINF` title and length of a track
+fn entry_info(track: &Track) -> EntryInfo {
+    EntryInfo {
+        title: format!("{} - {}", track.artist, track.title),
+        seconds: track.total_duration.as_secs() as i64,
+    }
+}
+
+/// Creates a TOML playlist in the music dir named after its title
+pub fn create(title: &str, tracks: &[Track]) -> crate::AppResult<Playlist> {
+    let name = title
+        .trim()
+        .replace(|c: char| c == '/' || c.is_control(), "_");
+    let name = if name.is_empty() {
+        String::from("Playlist")
+    } else {
+        name
+    };
+    let dir = PathBuf::from(&CONFIG.music_dir);
+    let mut path = dir.join(format!("{name}.toml"));
+    let mut number = 2;
+    while path.exists() {
+        path = dir.join(format!("{name} {number}.toml"));
+        number += 1;
     }
+    PlyData {
+        title: title.trim().to_string(),
+        tracks: tracks
+            .iter()
+            .map(|track| playlist_formats::relative_entry(&track.path, &dir))
+            .collect(),
+        rules: None,
+        info: vec![],
+        path: path.clone(),
+        source: String::new(),
+    }
+    .save()?;
+    Playlist::try_from(path)
+}
+
+/// Writes the loaded tracks of a playlist to an M3U8 file beside it, returns its path
+pub fn export_m3u(path: &Path) -> crate::AppResult<PathBuf> {
+    let playlist = Playlist::try_from(path.to_path_buf())?;
+    let dir = path.parent().unwrap_or(Path::new("/"));
+    let mut export = path.with_extension("m3u8");
+    if export == path {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        export = path.with_file_name(format!("{stem} (export).m3u8"));
+    }
+    let entries = playlist
+        .tracks
+        .iter()
+        .map(|track| {
+            (
+                playlist_formats::relative_entry(&track.path, dir),
+                Some(entry_info(track)),
+            )
+        })
+        .collect::<Vec<_>>();
+    write_atomic(
+        &export,
+        &playlist_formats::write_m3u(&playlist.title, &entries),
+    )?;
+    Ok(export)
+}
+
+/// Playlist tracks can be added to
+#[derive(Clone)]
+pub struct PlaylistTarget {
+    pub title: String,
+    pub path: PathBuf,
+    pub tracks: Vec<Track>,
+}
+
+impl Item for PlaylistTarget {}
+
+impl<'a> Into<Row<'a>> for PlaylistTarget {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new(self.title)])
+    }
+}
+
+impl Into<AppEvent> for PlaylistTarget {
+    fn into(self) -> AppEvent {
+        AppEvent::EditPlaylist(self.path, PlaylistEdit::Add(self.tracks))
+    }
+}
+
+/// Picks a playlist in the music dir to add tracks to or creates one
+///
+/// Only the playlist files are read, not their tracks
+pub fn playlist_picker(tracks: Vec<Track>) -> LinkedMenu {
+    let mut targets = std::fs::read_dir(&CONFIG.music_dir)
+        .into_iter()
+        .flat_map(|read_dir| read_dir.filter_map(|entry| Some(entry.ok()?.path())))
+        .filter(|path| playlist_formats::is_playlist(path))
+        .filter_map(|path| PlyData::try_from(path).ok())
+        .filter(|data| data.rules.is_none())
+        .map(|data| PlaylistTarget {
+            title: if data.title.is_empty() {
+                data.path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string()
+            } else {
+                data.title
+            },
+            path: data.path,
+            tracks: tracks.clone(),
+        })
+        .collect::<Vec<_>>();
+    targets.sort_by_key(|target| target.title.to_lowercase());
+    LinkedMenu::new(Box::new(MenuFrame::new([
+        Box::new(
+            TableMenu::new(targets, [Constraint::Fill(100)])
+                .with_header(Row::new([Cell::new("Add To Playlist")])),
+        ),
+        Box::new(TableMenu::new(
+            vec![AppEvent::NewPlaylist(tracks)],
+            [Constraint::Fill(100)],
+        )),
+        quick_menu(),
+    ])))
 }
@@ -0,0 +1,537 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Component, Path, PathBuf},
+};
+
+/// Extensions of the playlist files found in the music dir
+const EXTENSIONS: [&str; 5] = ["toml", "m3u", "m3u8", "pls", "xspf"];
+
+pub fn is_playlist(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+/// Title and length of an entry from an `#EXTINF` line or the `TitleN` and `LengthN` keys of a PLS
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryInfo {
+    pub title: String,
+    /// Seconds, -1 when unknown
+    pub seconds: i64,
+}
+
+/// Title and entries of an M3U or M3U8 playlist, `#PLAYLIST:` sets the title
+pub fn parse_m3u(text: &str) -> (String, Vec<(String, Option<EntryInfo>)>) {
+    let mut title = String::new();
+    let mut entries = vec![];
+    let mut info = None;
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if let Some(name) = line.strip_prefix("#PLAYLIST:") {
+            title = name.trim().to_string();
+        } else if let Some(extinf) = line.strip_prefix("#EXTINF:") {
+            // Attributes may follow the length before the comma
+            let (length, title) = extinf.split_once(',').unwrap_or((extinf, ""));
+            info = Some(EntryInfo {
+                title: title.trim().to_string(),
+                seconds: length
+                    .split_whitespace()
+                    .next()
+                    .and_then(|seconds| seconds.parse::<f64>().ok())
+                    .map_or(-1, |seconds| seconds as i64),
+            });
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push((line.to_string(), info.take()));
+        }
+    }
+    (title, entries)
+}
+
+/// Entries of a PLS playlist in the order of their `FileN` keys
+pub fn parse_pls(text: &str) -> Vec<(String, Option<EntryInfo>)> {
+    let mut files = BTreeMap::new();
+    let mut titles = HashMap::new();
+    let mut lengths = HashMap::new();
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim().to_string();
+        let number = |prefix: &str| key.strip_prefix(prefix)?.parse::<u32>().ok();
+        if let Some(number) = number("file") {
+            files.insert(number, value);
+        } else if let Some(number) = number("title") {
+            titles.insert(number, value);
+        } else if let Some(number) = number("length") {
+            lengths.insert(number, value.parse::<i64>().unwrap_or(-1));
+        }
+    }
+    files
+        .into_iter()
+        .map(|(number, entry)| {
+            let info = titles.remove(&number).map(|title| EntryInfo {
+                title,
+                seconds: lengths.get(&number).copied().unwrap_or(-1),
+            });
+            (entry, info)
+        })
+        .collect()
+}
+
+/// Title and track locations of an XSPF playlist
+///
+/// Locations are URIs, relative ones are decoded here and `file://` ones by [`resolve`]
+pub fn parse_xspf(text: &str) -> (String, Vec<String>) {
+    let head = text.split("<trackList").next().unwrap_or_default();
+    let title = tag_contents(head, "title")
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+    let entries = tag_contents(text, "location")
+        .into_iter()
+        .map(|location| {
+            if location.contains("://") {
+                location
+            } else {
+                percent_decode(&location)
+            }
+        })
+        .collect();
+    (title, entries)
+}
+
+/// Unescaped text of every element with a tag name, enough for the flat elements of XSPF
+fn tag_contents(text: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    let mut contents = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        contents.push(unescape(rest[..end].trim()));
+        rest = &rest[end + close.len()..];
+    }
+    contents
+}
+
+/// Replaces XML entities and character references
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match c {
+            Some(c) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Decodes `%XX` escapes leaving malformed ones as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| bytes.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Finds the file a playlist entry refers to
+///
+/// Entries may be absolute, relative to the playlist's dir, start with `~` or be `file://`
+/// URLs. Windows separators and case mismatches are tried when the path does not exist.
+/// Returns why the entry could not be resolved otherwise.
+pub fn resolve(entry: &str, dir: &Path) -> Result<PathBuf, String> {
+    let entry = entry.trim();
+    let entry = match entry.split_once("://") {
+        Some(("file", rest)) => percent_decode(rest.strip_prefix("localhost").unwrap_or(rest)),
+        Some((scheme, _)) => return Err(format!("{scheme} entries are not local files")),
+        None => entry.to_string(),
+    };
+    let mut candidates = vec![entry.clone()];
+    if entry.contains('\\') {
+        candidates.push(entry.replace('\\', "/"));
+    }
+    for candidate in &candidates {
+        let path = expand(candidate, dir);
+        if path.is_file() {
+            return Ok(path);
+        }
+        if let Some(path) = find_ignoring_case(&path) {
+            return Ok(path);
+        }
+    }
+    Err(String::from("file not found"))
+}
+
+/// Absolute normalized path of an entry, `~` being the home dir
+fn expand(entry: &str, dir: &Path) -> PathBuf {
+    let home = dirs::home_dir().unwrap_or_default();
+    let path = match entry.strip_prefix('~') {
+        Some("") => home,
+        Some(rest) if rest.starts_with('/') => home.join(rest.trim_start_matches('/')),
+        _ => dir.join(entry),
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// File whose path only differs in case
+fn find_ignoring_case(path: &Path) -> Option<PathBuf> {
+    let mut found = PathBuf::new();
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            found.push(component);
+            continue;
+        };
+        if found.join(name).exists() {
+            found.push(name);
+            continue;
+        }
+        let name = name.to_string_lossy().to_lowercase();
+        let entry = std::fs::read_dir(&found)
+            .ok()?
+            .flatten()
+            .find(|entry| entry.file_name().to_string_lossy().to_lowercase() == name)?;
+        found.push(entry.file_name());
+    }
+    found.is_file().then_some(found)
+}
+
+/// M3U playlist of entries, with `#EXTINF` lines where a title and seconds are given
+pub fn write_m3u(title: &str, entries: &[(String, Option<EntryInfo>)]) -> String {
+    let mut text = String::from("#EXTM3U\n");
+    if !title.is_empty() {
+        text.push_str(&format!("#PLAYLIST:{title}\n"));
+    }
+    for (entry, info) in entries {
+        if let Some(EntryInfo { title, seconds }) = info {
+            text.push_str(&format!("#EXTINF:{seconds},{title}\n"));
+        }
+        text.push_str(entry);
+        text.push('\n');
+    }
+    text
+}
+
+pub fn write_pls(entries: &[(String, Option<EntryInfo>)]) -> String {
+    let mut text = String::from("[playlist]\n");
+    for (number, (entry, info)) in entries.iter().enumerate() {
+        let number = number + 1;
+        text.push_str(&format!("File{number}={entry}\n"));
+        if let Some(EntryInfo { title, seconds }) = info {
+            text.push_str(&format!(
+                "Title{number}={title}\nLength{number}={seconds}\n"
+            ));
+        }
+    }
+    text.push_str(&format!("NumberOfEntries={}\nVersion=2\n", entries.len()));
+    text
+}
+
+/// XSPF playlist, entries that are not URLs are written as relative URIs
+pub fn write_xspf(title: &str, entries: &[String]) -> String {
+    let mut text = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n",
+    );
+    if !title.is_empty() {
+        text.push_str(&format!("  <title>{}</title>\n", escape(title)));
+    }
+    text.push_str("  <trackList>\n");
+    for entry in entries {
+        let location = if entry.contains("://") {
+            entry.clone()
+        } else {
+            percent_encode(entry)
+        };
+        text.push_str(&format!(
+            "    <track><location>{}</location></track>\n",
+            escape(&location)
+        ));
+    }
+    text.push_str("  </trackList>\n</playlist>\n");
+    text
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Encodes everything but unreserved characters and separators as `%XX`
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Entry for a file in a playlist in a dir, relative unless they only share the root
+pub fn relative_entry(path: &Path, dir: &Path) -> String {
+    let common = path
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common <= 1 {
+        return path.to_string_lossy().into_owned();
+    }
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    for component in path.components().skip(common) {
+        relative.push(component);
+    }
+    relative.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty dir of its own in the temp dir
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "{}-{}-{name}",
+            env!("CARGO_PKG_NAME"),
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "").unwrap();
+    }
+
+    #[test]
+    fn m3u_keeps_title_and_extinf() {
+        let (title, entries) = parse_m3u(
+            "\u{feff}#EXTM3U\n#PLAYLIST: Mix \n#EXTINF:123 tvg-id=\"x\",Artist - Song\nsong.mp3\n\n# comment\nother.flac\n",
+        );
+        assert_eq!(title, "Mix");
+        assert_eq!(
+            entries,
+            [
+                (
+                    String::from("song.mp3"),
+                    Some(EntryInfo {
+                        title: String::from("Artist - Song"),
+                        seconds: 123,
+                    }),
+                ),
+                (String::from("other.flac"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn m3u_round_trips() {
+        let entries = vec![
+            (
+                String::from("a.mp3"),
+                Some(EntryInfo {
+                    title: String::from("A"),
+                    seconds: -1,
+                }),
+            ),
+            (String::from("b.mp3"), None),
+        ];
+        assert_eq!(
+            parse_m3u(&write_m3u("Mix", &entries)),
+            (String::from("Mix"), entries)
+        );
+    }
+
+    #[test]
+    fn pls_orders_entries_by_number() {
+        let entries = parse_pls(
+            "[playlist]\nFile2=b.mp3\nfile1 = a.mp3\nTitle1=A\nLength1=60\nTitle2=B\nNumberOfEntries=2\n",
+        );
+        assert_eq!(
+            entries,
+            [
+                (
+                    String::from("a.mp3"),
+                    Some(EntryInfo {
+                        title: String::from("A"),
+                        seconds: 60,
+                    }),
+                ),
+                (
+                    String::from("b.mp3"),
+                    Some(EntryInfo {
+                        title: String::from("B"),
+                        seconds: -1,
+                    }),
+                ),
+            ]
+        );
+        assert_eq!(parse_pls(&write_pls(&entries)), entries);
+    }
+
+    #[test]
+    fn xspf_decodes_relative_locations_only() {
+        let (title, entries) = parse_xspf(
+            "<playlist><title>Rock &amp; Roll</title><trackList>\
+             <track><title>Song</title><location>dir/a%20b.mp3</location></track>\
+             <track><location>file:///music/c%20d.mp3</location></track>\
+             </trackList></playlist>",
+        );
+        assert_eq!(title, "Rock & Roll");
+        assert_eq!(entries, ["dir/a b.mp3", "file:///music/c%20d.mp3"]);
+    }
+
+    #[test]
+    fn xspf_round_trips() {
+        let entries = vec![String::from("a & b/c d.mp3")];
+        assert_eq!(
+            parse_xspf(&write_xspf("<Mix>", &entries)),
+            (String::from("<Mix>"), entries)
+        );
+    }
+
+    #[test]
+    fn resolves_relative_and_absolute_entries() {
+        let dir = temp_dir("resolve_paths");
+        let file = dir.join("music/a.mp3");
+        touch(&file);
+        assert_eq!(resolve("music/a.mp3", &dir), Ok(file.clone()));
+        assert_eq!(resolve(&file.to_string_lossy(), Path::new("/")), Ok(file));
+    }
+
+    #[test]
+    fn resolves_parent_dirs() {
+        let dir = temp_dir("resolve_parent");
+        let file = dir.join("music/a.mp3");
+        touch(&file);
+        assert_eq!(
+            resolve("../music/./a.mp3", &dir.join("playlists")),
+            Ok(file)
+        );
+    }
+
+    #[test]
+    fn resolves_file_urls() {
+        let dir = temp_dir("resolve_url");
+        let file = dir.join("a b.mp3");
+        touch(&file);
+        let url = format!("file://{}", percent_encode(&file.to_string_lossy()));
+        assert_eq!(resolve(&url, Path::new("/")), Ok(file.clone()));
+        let url = format!("file://localhost{}", file.display());
+        assert_eq!(resolve(&url, Path::new("/")), Ok(file));
+        assert!(resolve("http://example.com/a.mp3", &dir).is_err());
+    }
+
+    #[test]
+    fn resolves_windows_separators_and_case_mismatches() {
+        let dir = temp_dir("resolve_case");
+        let file = dir.join("Music/Song.MP3");
+        touch(&file);
+        assert_eq!(resolve("music\\song.mp3", &dir), Ok(file));
+        assert_eq!(
+            resolve("music/missing.mp3", &dir),
+            Err(String::from("file not found"))
+        );
+    }
+
+    #[test]
+    fn expands_the_home_dir() {
+        let home = dirs::home_dir().unwrap_or_default();
+        assert_eq!(expand("~", Path::new("/music")), home);
+        assert_eq!(
+            expand("~/music/a.mp3", Path::new("/music")),
+            home.join("music/a.mp3")
+        );
+        assert_eq!(
+            expand("~a.mp3", Path::new("/music")),
+            PathBuf::from("/music/~a.mp3")
+        );
+    }
+
+    #[test]
+    fn relative_entries_share_more_than_the_root() {
+        assert_eq!(
+            relative_entry(
+                Path::new("/home/me/music/a.mp3"),
+                Path::new("/home/me/lists")
+            ),
+            "../music/a.mp3"
+        );
+        assert_eq!(
+            relative_entry(
+                Path::new("/home/me/lists/a.mp3"),
+                Path::new("/home/me/lists")
+            ),
+            "a.mp3"
+        );
+        assert_eq!(
+            relative_entry(Path::new("/mnt/a.mp3"), Path::new("/home/me/lists")),
+            "/mnt/a.mp3"
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Flex, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Clear, Widget},
+};
+
+use crate::event::AppEvent;
+
+/// One line of text typed over the menus, submitting it sends an event made from the text
+#[derive(Clone)]
+pub struct Prompt {
+    pub title: String,
+    pub text: String,
+    submit: Arc<dyn Fn(String) -> AppEvent + Send + Sync>,
+}
+
+impl Prompt {
+    pub fn new(
+        title: impl Into<String>,
+        text: impl Into<String>,
+        submit: impl Fn(String) -> AppEvent + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            text: text.into(),
+            submit: Arc::new(submit),
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.text.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.text.pop();
+    }
+
+    pub fn submit(&self) -> AppEvent {
+        (self.submit)(self.text.clone())
+    }
+}
+
+impl Widget for &Prompt {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [area] = Layout::vertical([Constraint::Length(3)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        Clear.render(area, buf);
+        let block = Block::bordered().title(self.title.clone());
+        Line::from(vec![self.text.clone().into(), "_".yellow()]).render(block.inner(area), buf);
+        block.render(area, buf);
+    }
+}
@@ -0,0 +1,115 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::track::read_id3;
+
+/// Which ReplayGain tag is applied
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplayGainMode {
+    #[default]
+    Off,
+    Track,
+    Album,
+}
+
+/// ReplayGain tags of a track, gains in dB and peaks as linear amplitude
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+pub struct ReplayGain {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl ReplayGain {
+    /// Reads the ReplayGain tags of ID3, FLAC and MP4 files
+    pub fn read(path: &Path) -> Self {
+        let mut gain = Self::default();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        match extension.as_str() {
+            "mp3" | "wav" | "aiff" | "aif" => {
+                if let Some(tag) = read_id3(path) {
+                    for text in tag.extended_texts() {
+                        gain.set(&text.description, &text.value);
+                    }
+                }
+            }
+            "flac" => {
+                if let Ok(tag) = metaflac::Tag::read_from_path(path) {
+                    for key in KEYS {
+                        if let Some(value) =
+                            tag.get_vorbis(key).and_then(|mut values| values.next())
+                        {
+                            gain.set(key, value);
+                        }
+                    }
+                }
+            }
+            "m4a" | "m4b" | "mp4" => {
+                if let Ok(tag) = mp4ameta::Tag::read_from_path(path) {
+                    for key in KEYS {
+                        let name = key.to_lowercase();
+                        let ident = mp4ameta::FreeformIdent::new("com.apple.iTunes", &name);
+                        if let Some(value) = tag.strings_of(&ident).next() {
+                            gain.set(key, value);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        gain
+    }
+
+    /// Sets the field named by a tag key from a value like `-6.48 dB`
+    fn set(&mut self, key: &str, value: &str) {
+        let Ok(value) = value
+            .trim()
+            .trim_end_matches(|c: char| c.is_alphabetic())
+            .trim()
+            .parse::<f32>()
+        else {
+            return;
+        };
+        match key.to_lowercase().as_str() {
+            "replaygain_track_gain" => self.track_gain = Some(value),
+            "replaygain_track_peak" => self.track_peak = Some(value),
+            "replaygain_album_gain" => self.album_gain = Some(value),
+            "replaygain_album_peak" => self.album_peak = Some(value),
+            _ => {}
+        }
+    }
+
+    /// Gain and peak for a mode falling back to the other kind of tag
+    pub fn get(&self, mode: ReplayGainMode) -> Option<(f32, Option<f32>)> {
+        let track = self.track_gain.map(|gain| (gain, self.track_peak));
+        let album = self.album_gain.map(|gain| (gain, self.album_peak));
+        match mode {
+            ReplayGainMode::Off => None,
+            ReplayGainMode::Track => track.or(album),
+            ReplayGainMode::Album => album.or(track),
+        }
+    }
+}
+
+const KEYS: [&str; 4] = [
+    "REPLAYGAIN_TRACK_GAIN",
+    "REPLAYGAIN_TRACK_PEAK",
+    "REPLAYGAIN_ALBUM_GAIN",
+    "REPLAYGAIN_ALBUM_PEAK",
+];
+
+/// Linear amplification for a gain in dB, lowered so the peak does not clip
+pub fn amplification(gain: f32, peak: Option<f32>) -> f32 {
+    let factor = 10f32.powf(gain / 20.0);
+    match peak {
+        Some(peak) if peak > 0.0 => factor.min(1.0 / peak),
+        _ => factor,
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use hhmmss::Hhmmss;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    text::Text,
+    widgets::{Cell, Row},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CONFIG,
+    app::{AppState, quick_menu},
+    event::AppEvent,
+    logging::get_data_dir,
+    menus::{Item, LinkedMenu, Menu, MenuFrame, NavigationResult, TableMenu},
+    track::Track,
+};
+
+/// File in the data dir resume positions and bookmarks are saved to
+const RESUME_FILE: &str = "resume.toml";
+/// Positions this close to either end of a track are not worth resuming from
+const RESUME_MARGIN: Duration = Duration::from_secs(10);
+
+/// Named position in a track
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    /// Seconds into the track
+    pub position: f64,
+}
+
+impl Bookmark {
+    pub fn position(&self) -> Duration {
+        Duration::from_secs_f64(self.position.max(0.0))
+    }
+}
+
+/// Where long tracks were left and their bookmarks keyed by [`Track::path`]
+#[derive(Serialize, Deserialize, Default)]
+pub struct ResumeStore {
+    /// Data dir the store is saved in
+    #[serde(skip)]
+    directory: PathBuf,
+    /// Seconds into each track playback stopped at
+    #[serde(default)]
+    positions: HashMap<PathBuf, f64>,
+    #[serde(default)]
+    bookmarks: HashMap<PathBuf, Vec<Bookmark>>,
+}
+
+impl ResumeStore {
+    pub fn load(directory: &Path) -> Self {
+        let store: Self = std::fs::read_to_string(directory.join(RESUME_FILE))
+            .ok()
+            .and_then(|store| toml::from_str(&store).ok())
+            .unwrap_or_default();
+        Self {
+            directory: directory.to_path_buf(),
+            ..store
+        }
+    }
+
+    fn save(&self) -> color_eyre::Result<()> {
+        std::fs::create_dir_all(&self.directory)?;
+        std::fs::write(self.directory.join(RESUME_FILE), toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn position(&self, path: &Path) -> Option<Duration> {
+        self.positions
+            .get(path)
+            .map(|position| Duration::from_secs_f64(position.max(0.0)))
+    }
+
+    /// Saves where a track stopped, forgetting positions near its start or end
+    pub fn set_position(&mut self, track: &Track, position: Duration) -> color_eyre::Result<()> {
+        let near_end =
+            !track.total_duration.is_zero() && position + RESUME_MARGIN >= track.total_duration;
+        if position < RESUME_MARGIN || near_end {
+            if self.positions.remove(&track.path).is_none() {
+                return Ok(());
+            }
+        } else {
+            self.positions
+                .insert(track.path.clone(), position.as_secs_f64());
+        }
+        self.save()
+    }
+
+    pub fn bookmarks(&self, path: &Path) -> &[Bookmark] {
+        self.bookmarks.get(path).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// First numbered name none of the bookmarks of a track have
+    pub fn bookmark_name(&self, path: &Path) -> String {
+        let bookmarks = self.bookmarks(path);
+        (1..)
+            .map(|number| format!("Bookmark {number}"))
+            .find(|name| !bookmarks.iter().any(|bookmark| &bookmark.name == name))
+            .unwrap_or_default()
+    }
+
+    /// Bookmarks a position, a blank name is replaced with a numbered one
+    pub fn add_bookmark(
+        &mut self,
+        path: &Path,
+        position: Duration,
+        name: String,
+    ) -> color_eyre::Result<()> {
+        let name = match name.trim() {
+            "" => self.bookmark_name(path),
+            name => name.to_string(),
+        };
+        let bookmarks = self.bookmarks.entry(path.to_path_buf()).or_default();
+        bookmarks.push(Bookmark {
+            name,
+            position: position.as_secs_f64(),
+        });
+        bookmarks.sort_by(|a, b| a.position.total_cmp(&b.position));
+        self.save()
+    }
+
+    pub fn remove_bookmark(&mut self, path: &Path, index: usize) -> color_eyre::Result<()> {
+        if let Some(bookmarks) = self.bookmarks.get_mut(path) {
+            if index < bookmarks.len() {
+                bookmarks.remove(index);
+            }
+            if bookmarks.is_empty() {
+                self.bookmarks.remove(path);
+            }
+        }
+        self.save()
+    }
+}
+
+/// Is the track long or in one of the configured resume dirs
+pub fn is_resumable(track: &Track) -> bool {
+    let long = CONFIG
+        .resume_min_duration()
+        .is_some_and(|min| track.total_duration >= min);
+    long || CONFIG
+        .resume_dirs
+        .iter()
+        .any(|dir| track.path.starts_with(dir))
+}
+
+#[derive(Clone)]
+pub struct BookmarkEntry {
+    pub index: usize,
+    pub bookmark: Bookmark,
+    pub track: Track,
+}
+
+impl BookmarkEntry {
+    pub fn entries(track: &Track, bookmarks: &[Bookmark]) -> Vec<Self> {
+        bookmarks
+            .iter()
+            .enumerate()
+            .map(|(index, bookmark)| Self {
+                index,
+                bookmark: bookmark.clone(),
+                track: track.clone(),
+            })
+            .collect()
+    }
+}
+
+impl Item for BookmarkEntry {}
+
+impl Into<AppEvent> for BookmarkEntry {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(move || {
+            LinkedMenu::new(Box::new(MenuFrame::new([
+                Box::new(
+                    TableMenu::new(
+                        vec![
+                            AppEvent::BookmarkJump(self.track.clone(), self.bookmark.position()),
+                            AppEvent::BookmarkRemove(self.track.clone(), self.index),
+                        ],
+                        [Constraint::Fill(100)],
+                    )
+                    .with_header(Row::new([Cell::new(self.bookmark.name.clone())])),
+                ),
+                quick_menu(),
+            ])))
+        }))
+    }
+}
+
+impl<'a> Into<Row<'a>> for BookmarkEntry {
+    fn into(self) -> Row<'a> {
+        [
+            self.bookmark.name.clone(),
+            self.bookmark.position().hhmmss(),
+        ]
+        .iter()
+        .map(|elem| Cell::from(Text::from(elem.clone())))
+        .collect()
+    }
+}
+
+/// Bookmarks of a track, refreshed from the player while they are shown
+pub struct BookmarkMenu {
+    track: Track,
+    table: TableMenu<BookmarkEntry, [Constraint; 2]>,
+}
+
+impl Menu for BookmarkMenu {
+    fn up(&mut self) -> NavigationResult {
+        self.table.up()
+    }
+
+    fn down(&mut self) -> NavigationResult {
+        self.table.down()
+    }
+
+    fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
+        self.table.enter()
+    }
+
+    fn render(&mut self, area: Rect, buf: &mut Buffer, focused: bool) {
+        self.table.render(area, buf, focused);
+    }
+
+    fn constraint(&self) -> Constraint {
+        self.table.constraint()
+    }
+
+    fn tick(&mut self, app_state: &AppState) -> color_eyre::Result<()> {
+        let bookmarks = app_state.player.get_bookmarks(&self.track.path);
+        if !bookmarks
+            .iter()
+            .eq(self.table.items().iter().map(|entry| &entry.bookmark))
+        {
+            self.table
+                .set_items(BookmarkEntry::entries(&self.track, bookmarks));
+        }
+        Ok(())
+    }
+}
+
+pub fn bookmark_menu(track: &Track) -> BookmarkMenu {
+    BookmarkMenu {
+        track: track.clone(),
+        table: TableMenu::new(
+            BookmarkEntry::entries(
+                track,
+                ResumeStore::load(&get_data_dir()).bookmarks(&track.path),
+            ),
+            [Constraint::Min(5), Constraint::Length(8)],
+        )
+        .with_header(Row::new([Cell::new("Bookmarks"), Cell::new("Position")])),
+    }
+}
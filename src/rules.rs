@@ -0,0 +1,188 @@
+use std::{
+    cmp::Reverse,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{stats::Stats, track::Track};
+
+/// Rules choosing the tracks of a smart playlist from the library
+///
+/// Every rule given has to match, an empty set of rules matches the whole library
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(default)]
+pub struct Rules {
+    /// Artist ignoring case
+    pub artist: Option<String>,
+    /// Genre ignoring case
+    pub genre: Option<String>,
+    pub min_year: Option<i32>,
+    pub max_year: Option<i32>,
+    /// Glob over the whole path, `*` and `?` stay within a dir and `**` crosses dirs
+    pub path: Option<String>,
+    pub min_rating: Option<u8>,
+    pub min_plays: Option<u32>,
+    /// Files modified within this many days
+    pub added_within_days: Option<u64>,
+    /// Tracks not played within this many days
+    pub unplayed_within_days: Option<u64>,
+    pub sort: RuleSort,
+    /// Most tracks kept after sorting
+    pub limit: Option<usize>,
+}
+
+/// Order of the tracks of a smart playlist
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleSort {
+    #[default]
+    Path,
+    Title,
+    Artist,
+    Album,
+    Year,
+    Rating,
+    MostPlayed,
+    RecentlyPlayed,
+    RecentlyAdded,
+}
+
+impl Rules {
+    /// Tracks matching the rules in their order, cut to the limit
+    pub fn evaluate(&self, tracks: &[Track], stats: &Stats) -> Vec<Track> {
+        let now = SystemTime::now();
+        let days_ago = |days: u64| now - Duration::from_secs(days * 24 * 60 * 60);
+        let added_after = self.added_within_days.map(days_ago);
+        let played_before = self
+            .unplayed_within_days
+            .map(|days| seconds(days_ago(days)));
+
+        let mut matching = tracks
+            .iter()
+            .filter(|track| {
+                let stats = stats.get(&track.path);
+                self.artist
+                    .as_ref()
+                    .is_none_or(|artist| track.artist.eq_ignore_ascii_case(artist))
+                    && self
+                        .genre
+                        .as_ref()
+                        .is_none_or(|genre| track.genre.eq_ignore_ascii_case(genre))
+                    && self
+                        .min_year
+                        .is_none_or(|min| track.year.is_some_and(|year| year >= min))
+                    && self
+                        .max_year
+                        .is_none_or(|max| track.year.is_some_and(|year| year <= max))
+                    && self
+                        .path
+                        .as_ref()
+                        .is_none_or(|glob| glob_match(glob, &track.path.to_string_lossy()))
+                    && self.min_rating.is_none_or(|min| stats.rating >= min)
+                    && self.min_plays.is_none_or(|min| stats.plays >= min)
+                    && played_before
+                        .is_none_or(|before| stats.last_played.is_none_or(|played| played < before))
+                    && added_after.is_none_or(|after| added(track) >= after)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        match self.sort {
+            RuleSort::Path => matching.sort_by(|a, b| a.path.cmp(&b.path)),
+            RuleSort::Title => matching.sort_by_key(|track| track.title.to_lowercase()),
+            RuleSort::Artist => matching.sort_by_key(|track| track.artist.to_lowercase()),
+            RuleSort::Album => matching.sort_by(|a, b| {
+                (&a.album, a.track_number, &a.path).cmp(&(&b.album, b.track_number, &b.path))
+            }),
+            RuleSort::Year => matching.sort_by_key(|track| track.year),
+            RuleSort::Rating => {
+                matching.sort_by_key(|track| Reverse(stats.get(&track.path).rating))
+            }
+            RuleSort::MostPlayed => {
+                matching.sort_by_key(|track| Reverse(stats.get(&track.path).plays))
+            }
+            RuleSort::RecentlyPlayed => {
+                matching.sort_by_key(|track| Reverse(stats.get(&track.path).last_played))
+            }
+            RuleSort::RecentlyAdded => matching.sort_by_key(|track| Reverse(added(track))),
+        }
+        if let Some(limit) = self.limit {
+            matching.truncate(limit);
+        }
+        matching
+    }
+}
+
+/// When a track was added to the library going by its file's modification time when indexed
+fn added(track: &Track) -> SystemTime {
+    track.modified.unwrap_or(UNIX_EPOCH)
+}
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Matches a whole text against a glob
+fn glob_match(glob: &str, text: &str) -> bool {
+    let glob = glob.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    matches(&glob, &text)
+}
+
+fn matches(glob: &[char], text: &[char]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        // Any number of whole dirs
+        ['*', '*', '/', rest @ ..] => (0..=text.len())
+            .filter(|&skip| skip == 0 || text[skip - 1] == '/')
+            .any(|skip| matches(rest, &text[skip..])),
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|skip| matches(rest, &text[skip..])),
+        ['*', rest @ ..] => (0..=text.len())
+            .take_while(|&skip| skip == 0 || text[skip - 1] != '/')
+            .any(|skip| matches(rest, &text[skip..])),
+        ['?', rest @ ..] => text
+            .split_first()
+            .is_some_and(|(&c, text)| c != '/' && matches(rest, text)),
+        [c, rest @ ..] => text
+            .split_first()
+            .is_some_and(|(t, text)| t == c && matches(rest, text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn literal_globs_match_the_whole_text() {
+        assert!(glob_match("/music/a.mp3", "/music/a.mp3"));
+        assert!(!glob_match("/music/a", "/music/a.mp3"));
+        assert!(!glob_match("/music/a.mp3", "/music/a"));
+    }
+
+    #[test]
+    fn star_stays_within_a_dir() {
+        assert!(glob_match("/music/*.mp3", "/music/a.mp3"));
+        assert!(glob_match("/music/*", "/music/"));
+        assert!(!glob_match("/music/*.mp3", "/music/rock/a.mp3"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_character_but_a_separator() {
+        assert!(glob_match("/music/?.mp3", "/music/a.mp3"));
+        assert!(!glob_match("/music/?.mp3", "/music/ab.mp3"));
+        assert!(!glob_match("/music?a.mp3", "/music/a.mp3"));
+    }
+
+    #[test]
+    fn double_star_crosses_dirs() {
+        assert!(glob_match("/music/**/*.flac", "/music/a.flac"));
+        assert!(glob_match("/music/**/*.flac", "/music/rock/live/a.flac"));
+        assert!(!glob_match("/music/**/*.flac", "/musicx/a.flac"));
+        assert!(glob_match("**.flac", "/music/rock/a.flac"));
+        assert!(!glob_match("/music/**/*.flac", "/music/rock/a.mp3"));
+    }
+}
@@ -0,0 +1,223 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+use crate::{logging::get_data_dir, track::Track};
+
+/// Log of completed plays in the data dir for a desktop tool to submit later
+const LOG_FILE: &str = ".scrobbler.log";
+/// ListenBrainz export of the log in the data dir
+const LISTENBRAINZ_FILE: &str = "listens.json";
+/// Tracks shorter than this are never scrobbled
+const MIN_DURATION: Duration = Duration::from_secs(30);
+/// Listening this long scrobbles a track even if it is not half played
+const MAX_LISTEN: Duration = Duration::from_secs(4 * 60);
+
+/// Has a track been listened to long enough to scrobble, half of it or four minutes
+pub fn should_scrobble(duration: Duration, listened: Duration) -> bool {
+    duration >= MIN_DURATION && (listened >= duration / 2 || listened >= MAX_LISTEN)
+}
+
+/// Play of a track as written to the log
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scrobble {
+    pub artist: String,
+    pub album: String,
+    pub title: String,
+    pub track_number: Option<u16>,
+    /// Seconds
+    pub duration: u64,
+    /// Seconds since the epoch the track started playing
+    pub listened_at: u64,
+}
+
+impl Scrobble {
+    pub fn new(track: &Track, started: SystemTime) -> Self {
+        Self {
+            artist: track.artist.clone(),
+            album: track.album.clone(),
+            title: track.title.clone(),
+            track_number: track.track_number,
+            duration: track.total_duration.as_secs(),
+            listened_at: started
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+
+    /// Tab separated line of the AUDIOSCROBBLER/1.1 format
+    ///
+    /// Artist, album, title, track number, duration, `L` for listened, timestamp and MusicBrainz id
+    fn to_line(&self) -> String {
+        let field = |text: &str| text.replace(['\t', '\n', '\r'], " ");
+        format!(
+            "{}\t{}\t{}\t{}\t{}\tL\t{}\t\n",
+            field(&self.artist),
+            field(&self.album),
+            field(&self.title),
+            self.track_number
+                .map(|number| number.to_string())
+                .unwrap_or_default(),
+            self.duration,
+            self.listened_at,
+        )
+    }
+
+    /// Listened line of the log, skipped plays and malformed lines are ignored
+    fn from_line(line: &str) -> Option<Self> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 7 || fields[5] != "L" {
+            return None;
+        }
+        Some(Self {
+            artist: fields[0].to_string(),
+            album: fields[1].to_string(),
+            title: fields[2].to_string(),
+            track_number: fields[3].parse().ok(),
+            duration: fields[4].parse().ok()?,
+            listened_at: fields[6].parse().ok()?,
+        })
+    }
+
+    /// Listen as submitted to ListenBrainz
+    fn to_listen(&self) -> serde_json::Value {
+        let mut additional_info = json!({
+            "duration_ms": self.duration * 1000,
+            "submission_client": env!("CARGO_PKG_NAME"),
+            "submission_client_version": env!("CARGO_PKG_VERSION"),
+        });
+        if let Some(number) = self.track_number {
+            additional_info["tracknumber"] = json!(number);
+        }
+        let mut metadata = json!({
+            "artist_name": self.artist,
+            "track_name": self.title,
+            "additional_info": additional_info,
+        });
+        if !self.album.is_empty() {
+            metadata["release_name"] = json!(self.album);
+        }
+        json!({
+            "listened_at": self.listened_at,
+            "track_metadata": metadata,
+        })
+    }
+}
+
+fn log_path() -> PathBuf {
+    get_data_dir().join(LOG_FILE)
+}
+
+/// Appends a play to the log, writing the header first when the log is new
+pub fn record(scrobble: &Scrobble) -> std::io::Result<()> {
+    let path = log_path();
+    std::fs::create_dir_all(get_data_dir())?;
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    if file.metadata()?.len() == 0 {
+        write!(
+            file,
+            "#AUDIOSCROBBLER/1.1\n#TZ/UTC\n#CLIENT/{} {}\n",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        )?;
+    }
+    file.write_all(scrobble.to_line().as_bytes())
+}
+
+/// Plays written to the log so far
+fn load() -> Vec<Scrobble> {
+    std::fs::read_to_string(log_path())
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(Scrobble::from_line)
+        .collect()
+}
+
+/// Writes the log as a ListenBrainz import payload next to it
+///
+/// Returns where it was written
+pub fn export_listenbrainz() -> color_eyre::Result<PathBuf> {
+    let listens: Vec<serde_json::Value> = load().iter().map(Scrobble::to_listen).collect();
+    let path = get_data_dir().join(LISTENBRAINZ_FILE);
+    std::fs::write(
+        &path,
+        serde_json::to_string_pretty(&json!({
+            "listen_type": "import",
+            "payload": listens,
+        }))?,
+    )?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scrobble() -> Scrobble {
+        Scrobble {
+            artist: String::from("Artist"),
+            album: String::from("Album"),
+            title: String::from("Title"),
+            track_number: Some(3),
+            duration: 241,
+            listened_at: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn line_round_trips() {
+        let line = scrobble().to_line();
+        assert_eq!(
+            Scrobble::from_line(line.trim_end_matches('\n')),
+            Some(scrobble())
+        );
+    }
+
+    #[test]
+    fn line_round_trips_without_album_or_track_number() {
+        let scrobble = Scrobble {
+            album: String::new(),
+            track_number: None,
+            ..scrobble()
+        };
+        let line = scrobble.to_line();
+        assert_eq!(
+            Scrobble::from_line(line.trim_end_matches('\n')),
+            Some(scrobble)
+        );
+    }
+
+    #[test]
+    fn line_replaces_separators_in_fields() {
+        let line = Scrobble {
+            title: String::from("Two\tParts\nOf It"),
+            ..scrobble()
+        }
+        .to_line();
+        assert_eq!(line.lines().count(), 1);
+        assert_eq!(
+            Scrobble::from_line(line.trim_end_matches('\n')).map(|scrobble| scrobble.title),
+            Some(String::from("Two Parts Of It"))
+        );
+    }
+
+    #[test]
+    fn skipped_and_malformed_lines_are_ignored() {
+        assert_eq!(
+            Scrobble::from_line("A\tB\tC\t1\t200\tS\t1700000000\t"),
+            None
+        );
+        assert_eq!(
+            Scrobble::from_line("A\tB\tC\t1\tlong\tL\t1700000000\t"),
+            None
+        );
+        assert_eq!(Scrobble::from_line("A\tB\tC"), None);
+    }
+}
@@ -0,0 +1,281 @@
+use hhmmss::Hhmmss;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Stylize,
+    text::Line,
+    widgets::{Block, Cell, Clear, Row, Widget},
+};
+
+use crate::{
+    app::AppState,
+    device::Device,
+    event::AppEvent,
+    library::LIBRARY,
+    menus::{Item, Menu, NavigationResult, TableMenu},
+    playlist::Playlist,
+    track::Track,
+};
+
+/// Most results shown for a query
+const MAX_RESULTS: usize = 200;
+/// Track paths score this many times less than the tags since their dirs match many queries
+const PATH_WEIGHT: i64 = 3;
+
+/// Anything the search overlay can find
+#[derive(Clone)]
+pub enum SearchResult {
+    Track(Track),
+    Playlist(Playlist),
+    Device(Device),
+}
+
+impl SearchResult {
+    /// Texts the query is matched against
+    fn fields(&self) -> Vec<String> {
+        match self {
+            // Untagged tracks are titled by their file name
+            Self::Track(track) if track.title.is_empty() => vec![
+                track
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .to_string(),
+                track.artist.clone(),
+                track.album.clone(),
+            ],
+            Self::Track(track) => vec![
+                track.title.clone(),
+                track.artist.clone(),
+                track.album.clone(),
+            ],
+            Self::Playlist(playlist) => vec![playlist.title.clone()],
+            Self::Device(device) => vec![device.alias.clone()],
+        }
+    }
+
+    /// Best score of the query over the fields and the track's path
+    fn score(&self, query: &str) -> Option<i64> {
+        let path = match self {
+            Self::Track(track) => {
+                fuzzy_score(query, &track.path.to_string_lossy()).map(|score| score / PATH_WEIGHT)
+            }
+            _ => None,
+        };
+        self.fields()
+            .iter()
+            .filter_map(|field| fuzzy_score(query, field))
+            .max()
+            .max(path)
+    }
+}
+
+impl Item for SearchResult {}
+
+impl Into<AppEvent> for SearchResult {
+    fn into(self) -> AppEvent {
+        match self {
+            Self::Track(track) => track.into(),
+            Self::Playlist(playlist) => playlist.into(),
+            Self::Device(device) => device.into(),
+        }
+    }
+}
+
+impl<'a> Into<Row<'a>> for SearchResult {
+    fn into(self) -> Row<'a> {
+        let [kind, name, detail] = match self {
+            Self::Track(track) => [
+                String::from("Track"),
+                track.title,
+                format!("{} - {}", track.artist, track.album),
+            ],
+            Self::Playlist(playlist) => [
+                String::from("Playlist"),
+                playlist.title.clone(),
+                playlist.get_duration().hhmmss(),
+            ],
+            Self::Device(device) => [
+                String::from("Device"),
+                device.alias,
+                device.address.to_string(),
+            ],
+        };
+        Row::new([Cell::new(kind), Cell::new(name), Cell::new(detail)])
+    }
+}
+
+/// Scores how well the query's characters appear in order in the text, higher is better
+///
+/// Consecutive characters and characters starting a word score extra, gaps cost a little
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let mut query = query.chars().flat_map(char::to_lowercase).peekable();
+    query.peek()?;
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut consecutive = false;
+    let mut gap = 0;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+        if c == wanted {
+            query.next();
+            score += 1;
+            if consecutive {
+                score += 5;
+            }
+            if previous.is_none_or(|previous| !previous.is_alphanumeric()) {
+                score += 10;
+            }
+            score -= gap.min(5);
+            consecutive = true;
+            gap = 0;
+        } else {
+            consecutive = false;
+            gap += 1;
+        }
+        previous = Some(c);
+    }
+    match query.peek() {
+        Some(_) => None,
+        None => Some(score),
+    }
+}
+
+/// Overlay narrowing everything searchable down as the query is typed
+pub struct Search {
+    pub query: String,
+    candidates: Vec<SearchResult>,
+    matches: usize,
+    results: TableMenu<SearchResult, [Constraint; 3]>,
+}
+
+impl Search {
+    /// Collects the library's tracks, playlists and known Bluetooth devices already in memory
+    pub fn new(app_state: &AppState) -> Self {
+        let candidates = LIBRARY
+            .tracks()
+            .iter()
+            .cloned()
+            .map(SearchResult::Track)
+            .chain(
+                app_state
+                    .playlists
+                    .iter()
+                    .cloned()
+                    .map(SearchResult::Playlist),
+            )
+            .chain(
+                app_state
+                    .cloned_devices()
+                    .into_iter()
+                    .map(SearchResult::Device),
+            )
+            .collect();
+        let mut search = Self {
+            query: String::new(),
+            candidates,
+            matches: 0,
+            results: Self::table(vec![]),
+        };
+        search.update();
+        search
+    }
+
+    fn table(results: Vec<SearchResult>) -> TableMenu<SearchResult, [Constraint; 3]> {
+        let mut table = TableMenu::new(
+            results,
+            [
+                Constraint::Length(8),
+                Constraint::Fill(1),
+                Constraint::Fill(1),
+            ],
+        );
+        table.down();
+        table
+    }
+
+    /// Ranks the candidates for the query, the shorter match wins a tie
+    fn update(&mut self) {
+        let mut ranked: Vec<(i64, &SearchResult)> = self
+            .candidates
+            .iter()
+            .filter_map(|candidate| Some((candidate.score(&self.query)?, candidate)))
+            .collect();
+        self.matches = ranked.len();
+        ranked.sort_by_key(|(score, candidate)| {
+            (
+                -score,
+                candidate
+                    .fields()
+                    .first()
+                    .map(|field| field.len())
+                    .unwrap_or_default(),
+            )
+        });
+        self.results = Self::table(
+            ranked
+                .into_iter()
+                .take(MAX_RESULTS)
+                .map(|(_, candidate)| candidate.clone())
+                .collect(),
+        );
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.update();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.update();
+    }
+
+    /// Moves the selection keeping it on the results
+    pub fn up(&mut self) {
+        if !matches!(self.results.up(), NavigationResult::Ok) {
+            self.results.down();
+        }
+    }
+
+    pub fn down(&mut self) {
+        if !matches!(self.results.down(), NavigationResult::Ok) {
+            self.results.up();
+        }
+    }
+
+    /// Event of the selected result
+    pub fn enter(&mut self) -> color_eyre::Result<Option<AppEvent>> {
+        self.results.enter()
+    }
+}
+
+impl Widget for &mut Search {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let [_, area, _] = Layout::vertical([
+            Constraint::Percentage(10),
+            Constraint::Percentage(80),
+            Constraint::Percentage(10),
+        ])
+        .areas(area);
+        let [_, area, _] = Layout::horizontal([
+            Constraint::Percentage(10),
+            Constraint::Percentage(80),
+            Constraint::Percentage(10),
+        ])
+        .areas(area);
+        Clear.render(area, buf);
+        let block = Block::bordered()
+            .title("Search")
+            .title_bottom(Line::from(format!("{} matches", self.matches)).right_aligned());
+        let inner = block.inner(area);
+        block.render(area, buf);
+        let [query, results] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(100)]).areas(inner);
+        Line::from(vec!["/ ".yellow(), self.query.clone().into()]).render(query, buf);
+        self.results.render(results, buf, true);
+    }
+}
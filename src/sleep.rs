@@ -0,0 +1,157 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::Constraint,
+    widgets::{Cell, Row},
+};
+use strum_macros::Display;
+
+use crate::{
+    app::quick_menu,
+    audio_player::AudioPlayer,
+    event::AppEvent,
+    menus::{Item, LinkedMenu, MenuFrame, TableMenu},
+};
+
+/// How long playback fades out for before pausing
+const FADE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum SleepPreset {
+    #[strum(to_string = "{0} min")]
+    Minutes(u64),
+    #[strum(to_string = "End of Track")]
+    EndOfTrack,
+}
+
+const PRESETS: [SleepPreset; 4] = [
+    SleepPreset::Minutes(15),
+    SleepPreset::Minutes(30),
+    SleepPreset::Minutes(60),
+    SleepPreset::EndOfTrack,
+];
+
+enum SleepTarget {
+    At(Instant),
+    /// End of the player's listen with a number
+    EndOfTrack(u64),
+}
+
+/// Pauses playback after a while, fading it out first
+pub struct SleepTimer {
+    target: SleepTarget,
+}
+
+impl SleepTimer {
+    /// Timer for a preset, `None` for the end of the track when nothing is playing
+    pub fn new(preset: SleepPreset, player: &AudioPlayer) -> Option<Self> {
+        let target = match preset {
+            SleepPreset::Minutes(minutes) => {
+                SleepTarget::At(Instant::now() + Duration::from_secs(minutes * 60))
+            }
+            SleepPreset::EndOfTrack => {
+                player.get_current()?;
+                SleepTarget::EndOfTrack(player.get_listen())
+            }
+        };
+        Some(Self { target })
+    }
+
+    /// Has the timer run out
+    fn ended(&self, player: &AudioPlayer) -> bool {
+        match &self.target {
+            SleepTarget::At(deadline) => Instant::now() >= *deadline,
+            SleepTarget::EndOfTrack(listen) => player.listen_ended(*listen),
+        }
+    }
+
+    /// Time left until playback pauses, `None` while it waits for a track of unknown length to finish
+    pub fn remaining(&self, player: &AudioPlayer) -> Option<Duration> {
+        if self.ended(player) {
+            return Some(Duration::ZERO);
+        }
+        match &self.target {
+            SleepTarget::At(deadline) => Some(deadline.saturating_duration_since(Instant::now())),
+            SleepTarget::EndOfTrack(_) => player
+                .get_current()
+                .filter(|current| !current.total_duration.is_zero())
+                // Time left in the track playing at its speed
+                .map(|current| {
+                    current
+                        .total_duration
+                        .saturating_sub(player.get_pos())
+                        .div_f32(player.get_speed())
+                }),
+        }
+    }
+
+    /// Fades out towards the end of the timer, pausing and returning true when it runs out
+    ///
+    /// The end of track only runs out once the player finishes the track, its tagged length may be
+    /// off
+    pub fn tick(&self, player: &mut AudioPlayer) -> bool {
+        if self.ended(player) {
+            player.pause();
+            player.set_fade(1.0);
+            return true;
+        }
+        match self.remaining(player) {
+            Some(remaining) if remaining < FADE => {
+                player.set_fade(remaining.div_duration_f32(FADE))
+            }
+            // Full volume again once the end is further off, as after seeking back
+            _ => player.set_fade(1.0),
+        }
+        false
+    }
+}
+
+impl Item for SleepPreset {}
+
+impl Into<AppEvent> for SleepPreset {
+    fn into(self) -> AppEvent {
+        AppEvent::SetSleepTimer(self)
+    }
+}
+
+impl<'a> Into<Row<'a>> for SleepPreset {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new(self.to_string())])
+    }
+}
+
+#[derive(Clone)]
+pub struct SleepItem;
+
+impl Item for SleepItem {}
+
+impl SleepItem {
+    pub fn to_menu(self) -> TableMenu<SleepItem, [Constraint; 1]> {
+        TableMenu::new(vec![self], [Constraint::Fill(100)])
+    }
+}
+
+impl Into<AppEvent> for SleepItem {
+    fn into(self) -> AppEvent {
+        AppEvent::Push(Arc::new(|| sleep_menu()))
+    }
+}
+
+impl<'a> Into<Row<'a>> for SleepItem {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new("Sleep Timer")])
+    }
+}
+
+pub fn sleep_menu() -> LinkedMenu {
+    LinkedMenu::new(Box::new(MenuFrame::new([
+        Box::new(
+            TableMenu::new(PRESETS.to_vec(), [Constraint::Fill(100)])
+                .with_header(Row::new([Cell::new("Sleep After")])),
+        ),
+        quick_menu(),
+    ])))
+}
@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use chrono::{Local, TimeZone};
+use lazy_static::lazy_static;
+use ratatui::{
+    layout::Constraint,
+    widgets::{Cell, Row},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CONFIG,
+    event::AppEvent,
+    logging::get_data_dir,
+    menus::{Item, TableMenu},
+    trace_dbg,
+    track::Track,
+};
+
+/// File in the data dir play statistics and ratings are saved to
+const STATS_FILE: &str = "stats.toml";
+pub const MAX_RATING: u8 = 5;
+
+lazy_static! {
+    pub static ref STATS: Stats = Stats::load();
+}
+
+/// How often a track was played or skipped and how it is rated
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct TrackStats {
+    pub plays: u32,
+    /// Seconds since the epoch
+    pub last_played: Option<u64>,
+    pub skips: u32,
+    /// Zero to five stars
+    pub rating: u8,
+    pub favorite: bool,
+}
+
+impl TrackStats {
+    /// Stars of the rating with a heart for favorites
+    pub fn stars(&self) -> String {
+        let rating = self.rating.min(MAX_RATING) as usize;
+        let mut stars = "★".repeat(rating) + &"☆".repeat(MAX_RATING as usize - rating);
+        if self.favorite {
+            stars.push_str(" ♥");
+        }
+        stars
+    }
+}
+
+/// Statistics of every track keyed by [`Track::path`] saved on another thread whenever they change
+#[derive(Default)]
+pub struct Stats {
+    tracks: RwLock<HashMap<PathBuf, TrackStats>>,
+    /// Have the statistics changed since it was last taken
+    changed: AtomicBool,
+    /// Is a save running on another thread
+    saving: AtomicBool,
+    /// Have the statistics changed since the running save read them
+    unsaved: AtomicBool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StatsFile {
+    #[serde(default)]
+    tracks: HashMap<PathBuf, TrackStats>,
+}
+
+impl Stats {
+    fn load() -> Self {
+        let file: StatsFile = std::fs::read_to_string(get_data_dir().join(STATS_FILE))
+            .ok()
+            .and_then(|file| toml::from_str(&file).ok())
+            .unwrap_or_default();
+        Self {
+            tracks: RwLock::new(file.tracks),
+            changed: AtomicBool::new(false),
+            saving: AtomicBool::new(false),
+            unsaved: AtomicBool::new(false),
+        }
+    }
+
+    fn save(tracks: &HashMap<PathBuf, TrackStats>) -> color_eyre::Result<()> {
+        let directory = get_data_dir();
+        std::fs::create_dir_all(&directory)?;
+        std::fs::write(
+            directory.join(STATS_FILE),
+            toml::to_string(&StatsFile {
+                tracks: tracks.clone(),
+            })?,
+        )?;
+        Ok(())
+    }
+
+    pub fn get(&self, path: &Path) -> TrackStats {
+        self.tracks
+            .read()
+            .ok()
+            .and_then(|tracks| tracks.get(path).copied())
+            .unwrap_or_default()
+    }
+
+    /// Saves on another thread, changes made while it saves are saved once it is done
+    fn save_later(&'static self) {
+        self.unsaved.store(true, Ordering::Relaxed);
+        if self.saving.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        std::thread::spawn(move || {
+            loop {
+                self.unsaved.store(false, Ordering::Relaxed);
+                let tracks = self
+                    .tracks
+                    .read()
+                    .map(|tracks| tracks.clone())
+                    .unwrap_or_default();
+                if let Err(err) = Self::save(&tracks) {
+                    trace_dbg!(&err);
+                }
+                self.saving.store(false, Ordering::Relaxed);
+                // Save again unless nothing changed meanwhile or an update started a new save
+                if !self.unsaved.load(Ordering::Relaxed)
+                    || self.saving.swap(true, Ordering::Relaxed)
+                {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Waits for the last change to be saved, before exiting
+    pub fn flush(&self) {
+        while self.saving.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Changes the statistics of a track, forgetting them once they are back to the default
+    fn update(&'static self, path: &Path, change: impl FnOnce(&mut TrackStats)) {
+        let Ok(mut tracks) = self.tracks.write() else {
+            return;
+        };
+        let stats = tracks.entry(path.to_path_buf()).or_default();
+        change(stats);
+        if *stats == TrackStats::default() {
+            tracks.remove(path);
+        }
+        drop(tracks);
+        self.save_later();
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the statistics changed since the last call
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Counts a track played to its end
+    pub fn record_play(&'static self, path: &Path) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.update(path, |stats| {
+            stats.plays += 1;
+            stats.last_played = Some(now);
+        });
+    }
+
+    /// Counts a track left before its end
+    pub fn record_skip(&'static self, path: &Path) {
+        self.update(path, |stats| stats.skips += 1);
+    }
+
+    pub fn set_rating(&'static self, path: &Path, rating: u8) {
+        self.update(path, |stats| stats.rating = rating.min(MAX_RATING));
+    }
+
+    pub fn toggle_favorite(&'static self, path: &Path) {
+        self.update(path, |stats| stats.favorite = !stats.favorite);
+    }
+
+    pub fn reset(&'static self, path: &Path) {
+        self.update(path, |stats| *stats = TrackStats::default());
+    }
+}
+
+/// Row of the statistics menu of a track, rows read the current statistics as they render
+#[derive(Clone)]
+pub enum StatsAction {
+    Rate(PathBuf),
+    Favorite(PathBuf),
+    Reset(PathBuf),
+    /// Writes the scrobble log of every track as ListenBrainz JSON
+    ExportListens,
+}
+
+impl Item for StatsAction {}
+
+impl<'a> Into<Row<'a>> for StatsAction {
+    fn into(self) -> Row<'a> {
+        Row::new([Cell::new(match self {
+            Self::Rate(path) => format!("Rating {}", STATS.get(&path).stars()),
+            Self::Favorite(path) => match STATS.get(&path).favorite {
+                true => String::from("Unfavorite"),
+                false => String::from("Favorite"),
+            },
+            Self::Reset(path) => {
+                let stats = STATS.get(&path);
+                let last_played = stats
+                    .last_played
+                    .and_then(|seconds| Local.timestamp_opt(seconds as i64, 0).single())
+                    .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| String::from("never"));
+                format!(
+                    "Reset Statistics: {} plays, {} skips, last played {last_played}",
+                    stats.plays, stats.skips
+                )
+            }
+            Self::ExportListens => String::from("Export Listens As ListenBrainz JSON"),
+        })])
+    }
+}
+
+impl Into<AppEvent> for StatsAction {
+    fn into(self) -> AppEvent {
+        match self {
+            // Cycles through the ratings
+            Self::Rate(path) => {
+                let rating = (STATS.get(&path).rating + 1) % (MAX_RATING + 1);
+                AppEvent::SetRating(path, rating)
+            }
+            Self::Favorite(path) => AppEvent::ToggleFavorite(path),
+            Self::Reset(path) => AppEvent::ResetStats(path),
+            Self::ExportListens => AppEvent::ExportListens,
+        }
+    }
+}
+
+/// Widths of the play count and rating columns when the config shows them
+pub fn widths() -> Vec<Constraint> {
+    match CONFIG.stats_columns {
+        true => vec![Constraint::Length(5), Constraint::Length(7)],
+        false => vec![],
+    }
+}
+
+/// Header cells of the play count and rating columns when the config shows them
+pub fn header() -> Vec<Cell<'static>> {
+    match CONFIG.stats_columns {
+        true => vec![Cell::new("Plays"), Cell::new("Rating")],
+        false => vec![],
+    }
+}
+
+/// Play count and rating of a track when the config shows them
+pub fn track_cells(path: &Path) -> Vec<String> {
+    if !CONFIG.stats_columns {
+        return vec![];
+    }
+    let stats = STATS.get(path);
+    vec![stats.plays.to_string(), stats.stars()]
+}
+
+/// Total play count and average rating of tracks when the config shows them
+pub fn tracks_cells(tracks: &[Track]) -> Vec<String> {
+    if !CONFIG.stats_columns {
+        return vec![];
+    }
+    let stats: Vec<TrackStats> = tracks.iter().map(|track| STATS.get(&track.path)).collect();
+    let plays: u32 = stats.iter().map(|stats| stats.plays).sum();
+    let rating = match stats.len() {
+        0 => 0,
+        len => {
+            let total: usize = stats.iter().map(|stats| stats.rating as usize).sum();
+            ((total + len / 2) / len) as u8
+        }
+    };
+    let average = TrackStats {
+        rating,
+        ..TrackStats::default()
+    };
+    vec![plays.to_string(), average.stars()]
+}
+
+/// Rating, favorite flag and play counts of a track and the export of the scrobble log
+pub fn stats_menu(track: &Track) -> TableMenu<StatsAction, [Constraint; 1]> {
+    TableMenu::new(
+        vec![
+            StatsAction::Rate(track.path.clone()),
+            StatsAction::Favorite(track.path.clone()),
+            StatsAction::Reset(track.path.clone()),
+            StatsAction::ExportListens,
+        ],
+        [Constraint::Fill(100)],
+    )
+}
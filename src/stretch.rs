@@ -0,0 +1,367 @@
+use std::{
+    collections::VecDeque,
+    f32::consts::PI,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source, source::SeekError};
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 3.0;
+/// Length of the segments overlapped by the stretcher
+const WINDOW: Duration = Duration::from_millis(40);
+/// How far from its nominal position a segment may be taken to line up with the last one
+const TOLERANCE: Duration = Duration::from_millis(8);
+/// Only every few frames are compared when lining segments up
+const CORRELATION_STRIDE: usize = 4;
+
+/// Speed shared between the player and the sources it plays
+#[derive(Clone)]
+pub struct SpeedControl {
+    /// Bits of the speed as an `f32`
+    speed: Arc<AtomicU32>,
+    /// Bits of a factor slowing the speed down further as an `f32`
+    slowdown: Arc<AtomicU32>,
+    preserve_pitch: Arc<AtomicBool>,
+}
+
+impl Default for SpeedControl {
+    fn default() -> Self {
+        Self {
+            speed: Arc::new(AtomicU32::new(1f32.to_bits())),
+            slowdown: Arc::new(AtomicU32::new(1f32.to_bits())),
+            preserve_pitch: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+impl SpeedControl {
+    pub fn speed(&self) -> f32 {
+        f32::from_bits(self.speed.load(Ordering::Relaxed))
+    }
+
+    /// Sets the speed rounded to a hundredth
+    pub fn set_speed(&self, speed: f32) {
+        let speed = ((speed * 100.0).round() / 100.0).clamp(MIN_SPEED, MAX_SPEED);
+        self.speed.store(speed.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn set_slowdown(&self, slowdown: f32) {
+        self.slowdown
+            .store(slowdown.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Speed sources play at, slowed down and limited to the supported range
+    pub fn effective_speed(&self) -> f32 {
+        let slowdown = f32::from_bits(self.slowdown.load(Ordering::Relaxed));
+        (self.speed() * slowdown).clamp(MIN_SPEED, MAX_SPEED)
+    }
+
+    pub fn preserves_pitch(&self) -> bool {
+        self.preserve_pitch.load(Ordering::Relaxed)
+    }
+
+    pub fn set_preserve_pitch(&self, preserve_pitch: bool) {
+        self.preserve_pitch.store(preserve_pitch, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Playing at normal speed
+    Passthrough,
+    /// Overlapping segments of the source, keeping the pitch
+    Stretch,
+    /// Interpolating between samples, changing the pitch
+    Resample,
+}
+
+/// Source played at the speed of a [`SpeedControl`], following changes while playing
+///
+/// Pitch is kept with WSOLA, segments of the source are overlapped a half window apart after
+/// being shifted to line up with the continuation of the previous segment
+pub struct Stretch<S> {
+    inner: S,
+    control: SpeedControl,
+    mode: Mode,
+    channels: usize,
+    /// Half of the window in frames, the distance segments are overlapped at
+    half: usize,
+    tolerance: usize,
+    /// Hann window over both halves
+    window: Vec<f32>,
+    /// Interleaved samples read from the source and not yet done with
+    input: VecDeque<Sample>,
+    exhausted: bool,
+    /// Frame of `input` to read from next, the nominal start of the next segment when stretching
+    cursor: f64,
+    /// Frame of `input` continuing the last segment, only used when stretching
+    template: usize,
+    /// Windowed second half of the last segment
+    tail: Vec<Sample>,
+    output: VecDeque<Sample>,
+    /// Channel of the next sample passed straight through, the mode only switches between frames
+    channel: usize,
+}
+
+impl<S: Source> Stretch<S> {
+    pub fn new(inner: S, control: SpeedControl) -> Self {
+        let channels = inner.channels().max(1) as usize;
+        let half = (WINDOW.as_secs_f64() * inner.sample_rate() as f64 / 2.0).max(1.0) as usize;
+        let tolerance = (TOLERANCE.as_secs_f64() * inner.sample_rate() as f64) as usize;
+        let window = (0..half * 2)
+            .map(|frame| 0.5 - 0.5 * (PI * frame as f32 / half as f32).cos())
+            .collect();
+        Self {
+            inner,
+            control,
+            mode: Mode::Passthrough,
+            channels,
+            half,
+            tolerance,
+            window,
+            input: VecDeque::new(),
+            exhausted: false,
+            cursor: 0.0,
+            template: 0,
+            tail: vec![],
+            output: VecDeque::new(),
+            channel: 0,
+        }
+    }
+
+    fn frames(&self) -> usize {
+        self.input.len() / self.channels
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> Sample {
+        self.input[frame * self.channels + channel]
+    }
+
+    /// Reads from the source until `input` holds `frames` frames or the source ends
+    fn fill(&mut self, frames: usize) -> bool {
+        while self.frames() < frames && !self.exhausted {
+            for _ in 0..self.channels {
+                match self.inner.next() {
+                    Some(sample) => self.input.push_back(sample),
+                    None => {
+                        self.exhausted = true;
+                        break;
+                    }
+                }
+            }
+        }
+        self.frames() >= frames
+    }
+
+    /// Drops the frames of `input` before `frame`
+    fn trim(&mut self, frame: usize) {
+        let frame = frame.min(self.frames());
+        self.input.drain(..frame * self.channels);
+        self.cursor -= frame as f64;
+        self.template = self.template.saturating_sub(frame);
+    }
+
+    fn mode(&self) -> Mode {
+        if self.control.effective_speed() == 1.0 {
+            Mode::Passthrough
+        } else if self.control.preserves_pitch() {
+            Mode::Stretch
+        } else {
+            Mode::Resample
+        }
+    }
+
+    /// Fills `output` with the next stretch of audio, returns false once the source is done
+    fn step(&mut self) -> bool {
+        let mode = self.mode();
+        if mode != self.mode {
+            self.switch(mode);
+            if !self.output.is_empty() {
+                return true;
+            }
+        }
+        match self.mode {
+            Mode::Passthrough => self.passthrough(),
+            Mode::Resample => self.resample(),
+            Mode::Stretch => self.stretch(),
+        }
+    }
+
+    fn switch(&mut self, mode: Mode) {
+        if self.mode == Mode::Stretch {
+            // The tail and the continuation of the last segment add up to the source itself
+            self.fill(self.template + self.half);
+            let end = (self.template + self.half).min(self.frames());
+            let start = self.template.min(end);
+            self.output
+                .extend(self.input.range(start * self.channels..end * self.channels));
+            self.cursor = end as f64;
+            self.tail.clear();
+        }
+        if mode == Mode::Stretch {
+            // Start as if the previous segment ended on the cursor
+            self.template = self.cursor as usize;
+            self.fill(self.template + self.half);
+            let end = (self.template + self.half).min(self.frames());
+            let mut tail = vec![0.0; self.half * self.channels];
+            for frame in 0..end.saturating_sub(self.template) {
+                for channel in 0..self.channels {
+                    tail[frame * self.channels + channel] = self
+                        .sample(self.template + frame, channel)
+                        * self.window[self.half + frame];
+                }
+            }
+            self.tail = tail;
+        }
+        self.mode = mode;
+    }
+
+    fn passthrough(&mut self) -> bool {
+        let start = self.cursor as usize;
+        self.fill(start + self.half);
+        let end = (start + self.half).min(self.frames());
+        self.output
+            .extend(self.input.range(start * self.channels..end * self.channels));
+        self.cursor = end as f64;
+        self.trim(end);
+        end > start
+    }
+
+    fn resample(&mut self) -> bool {
+        let speed = self.control.effective_speed() as f64;
+        for _ in 0..self.half {
+            let frame = self.cursor as usize;
+            if !self.fill(frame + 2) {
+                break;
+            }
+            let fraction = (self.cursor - frame as f64) as f32;
+            for channel in 0..self.channels {
+                let (from, to) = (self.sample(frame, channel), self.sample(frame + 1, channel));
+                self.output.push_back(from + (to - from) * fraction);
+            }
+            self.cursor += speed;
+        }
+        self.trim(self.cursor as usize);
+        !self.output.is_empty()
+    }
+
+    fn stretch(&mut self) -> bool {
+        if self.exhausted && self.input.is_empty() {
+            return false;
+        }
+        let window = self.half * 2;
+        let nominal = self.cursor as usize;
+        let earliest = nominal.saturating_sub(self.tolerance);
+        if !self.fill((nominal + self.tolerance).max(self.template) + window) {
+            // Play out what is left of the source unstretched
+            self.switch(Mode::Passthrough);
+            let start = (self.cursor as usize * self.channels).min(self.input.len());
+            self.output.extend(self.input.drain(start..));
+            self.input.clear();
+            self.cursor = 0.0;
+            self.template = 0;
+            return true;
+        }
+
+        // Segment lining up best with the continuation of the last one
+        let best = (earliest..=nominal + self.tolerance)
+            .step_by(2)
+            .max_by(|a, b| self.correlation(*a).total_cmp(&self.correlation(*b)))
+            .unwrap_or(nominal);
+
+        for frame in 0..self.half {
+            for channel in 0..self.channels {
+                let sample = self.sample(best + frame, channel) * self.window[frame];
+                self.output
+                    .push_back(self.tail[frame * self.channels + channel] + sample);
+            }
+        }
+        for frame in 0..self.half {
+            for channel in 0..self.channels {
+                self.tail[frame * self.channels + channel] =
+                    self.sample(best + self.half + frame, channel) * self.window[self.half + frame];
+            }
+        }
+
+        self.template = best + self.half;
+        self.cursor += self.half as f64 * self.control.effective_speed() as f64;
+        let keep = self
+            .template
+            .min((self.cursor as usize).saturating_sub(self.tolerance));
+        self.trim(keep);
+        true
+    }
+
+    /// Similarity of the segment starting at `start` to the continuation of the last segment
+    fn correlation(&self, start: usize) -> f32 {
+        (0..self.half)
+            .step_by(CORRELATION_STRIDE)
+            .map(|frame| {
+                (0..self.channels)
+                    .map(|channel| {
+                        self.sample(self.template + frame, channel)
+                            * self.sample(start + frame, channel)
+                    })
+                    .sum::<f32>()
+            })
+            .sum()
+    }
+}
+
+impl<S: Source> Iterator for Stretch<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(sample) = self.output.pop_front() {
+                return Some(sample);
+            }
+            if self.mode == Mode::Passthrough
+                && self.input.is_empty()
+                && (self.channel != 0 || self.mode() == Mode::Passthrough)
+            {
+                self.channel = (self.channel + 1) % self.channels;
+                return self.inner.next();
+            }
+            if !self.step() {
+                return None;
+            }
+        }
+    }
+}
+
+impl<S: Source> Source for Stretch<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> rodio::ChannelCount {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> rodio::SampleRate {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.inner.try_seek(pos)?;
+        self.mode = Mode::Passthrough;
+        self.input.clear();
+        self.output.clear();
+        self.tail.clear();
+        self.channel = 0;
+        self.exhausted = false;
+        self.cursor = 0.0;
+        self.template = 0;
+        Ok(())
+    }
+}
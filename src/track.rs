@@ -2,21 +2,33 @@ use audiotags::Tag;
 use color_eyre::eyre::Context;
 use hhmmss::Hhmmss;
 use ratatui::{
-    text::Text,
+    layout::Constraint,
+    text::{Line, Text},
     widgets::{Cell, Row},
 };
 // use hhmmss::Hhmmss;
-use rodio::Decoder;
+use rodio::{Decoder, Source, source::Amplify};
 
 use std::{
     fmt::Debug,
     fs::{File, OpenOptions},
     io::BufReader,
-    path::PathBuf,
-    time::Duration,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
 
-use crate::{event::AppEvent, menus::Item};
+use crate::{
+    CONFIG,
+    app::quick_menu,
+    chapters::{self, Chapter, chapter_menu},
+    event::AppEvent,
+    loudness,
+    menus::{Item, LinkedMenu, MenuFrame, TextMenu, action_menu},
+    replaygain::{self, ReplayGain, ReplayGainMode},
+    resume::bookmark_menu,
+    stats::{self, stats_menu},
+};
 
 // make into decoder for track and on cp do not rebuild
 
@@ -25,18 +37,56 @@ pub struct Track {
     pub path: PathBuf,
     pub title: String,
     pub artist: String,
+    pub album: String,
+    pub genre: String,
+    pub year: Option<i32>,
+    pub track_number: Option<u16>,
     pub total_duration: Duration,
+    pub replay_gain: ReplayGain,
+    pub chapters: Arc<[Chapter]>,
+    /// When the file was last modified, standing in for when it was added
+    pub modified: Option<SystemTime>,
 }
 
 impl Track {
-    pub fn decode(&self) -> color_eyre::Result<Decoder<BufReader<File>>> {
-        Decoder::try_from(
+    pub fn decode(&self) -> color_eyre::Result<Amplify<Decoder<BufReader<File>>>> {
+        Ok(Decoder::try_from(
             OpenOptions::new()
                 .read(true)
                 .open(self.path.clone())
                 .wrap_err("Failed to open file!")?,
         )
-        .wrap_err("Rodio decoder err!")
+        .wrap_err("Rodio decoder err!")?
+        .amplify(self.amplification()))
+    }
+
+    /// Index of the chapter playing at a position
+    pub fn chapter_index_at(&self, pos: Duration) -> Option<usize> {
+        self.chapters
+            .iter()
+            .rposition(|chapter| chapter.start <= pos)
+    }
+
+    /// Linear gain for the configured ReplayGain mode
+    ///
+    /// Untagged tracks use their analysed loudness when analysis is enabled
+    pub fn amplification(&self) -> f32 {
+        if CONFIG.replaygain == ReplayGainMode::Off {
+            return 1.0;
+        }
+        if let Some((gain, peak)) = self.replay_gain.get(CONFIG.replaygain) {
+            return replaygain::amplification(gain, peak);
+        }
+        if !CONFIG.loudness_analysis {
+            return 1.0;
+        }
+        match loudness::cached_gain(&self.path) {
+            Some((gain, peak)) => replaygain::amplification(gain, Some(peak)),
+            None => {
+                loudness::analyze_in_background(self.path.clone());
+                1.0
+            }
+        }
     }
 }
 
@@ -54,6 +104,15 @@ impl TryFrom<PathBuf> for Track {
             path: value.clone(),
             title: tag.title().unwrap_or_default().to_string(),
             artist: tag.artist().unwrap_or_default().to_string(),
+            album: tag.album_title().unwrap_or_default().to_string(),
+            genre: tag.genre().unwrap_or_default().to_string(),
+            year: tag.year(),
+            track_number: tag.track_number(),
+            replay_gain: ReplayGain::read(&value),
+            chapters: chapters::read(&value).into(),
+            modified: std::fs::metadata(&value)
+                .and_then(|metadata| metadata.modified())
+                .ok(),
             total_duration: match tag.duration() {
                 Some(dur) => Duration::from_secs_f64(dur),
                 None => mp3_duration::from_path(value).unwrap_or_default(),
@@ -62,9 +121,49 @@ impl TryFrom<PathBuf> for Track {
     }
 }
 
+/// Reads the ID3 tag of a file, WAV and AIFF files keep it in a chunk of their own
+// The chunk readers are deprecated for sniffing the format, the extension already tells it
+#[allow(deprecated)]
+pub fn read_id3(path: &Path) -> Option<id3::Tag> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "wav" => id3::Tag::read_from_wav_path(path),
+        "aiff" | "aif" => id3::Tag::read_from_aiff_path(path),
+        _ => id3::Tag::read_from_path(path),
+    }
+    .ok()
+}
+
+pub fn track_info_menu(track: Track) -> LinkedMenu {
+    LinkedMenu::new(Box::new(MenuFrame::new([
+        Box::new(TextMenu(Text::from(vec![
+            Line::from(format!("Title: {}", track.title)),
+            Line::from(format!("Artist: {}", track.artist)),
+            Line::from(format!("Album: {}", track.album)),
+            Line::from(format!("Duration: {}", track.total_duration.hhmmss())),
+            Line::from(format!("Path: {}", track.path.display())),
+        ]))),
+        Box::new(stats_menu(&track)),
+        Box::new(chapter_menu(&track)),
+        Box::new(bookmark_menu(&track)),
+        quick_menu(),
+    ])))
+}
+
 impl Into<AppEvent> for Track {
     fn into(self) -> AppEvent {
-        AppEvent::Play(vec![self])
+        AppEvent::Push(Arc::new(move || {
+            let track = self.clone();
+            action_menu(
+                self.title.clone(),
+                vec![self.clone()],
+                Arc::new(move || track_info_menu(track.clone())),
+            )
+        }))
     }
 }
 
@@ -75,10 +174,33 @@ impl<'a> Into<Row<'a>> for Track {
             self.artist.clone(),
             self.total_duration.hhmmss(),
         ]
-        .iter()
-        .map(|elem| Cell::from(Text::from(format!("{elem}"))))
+        .into_iter()
+        .chain(stats::track_cells(&self.path))
+        .map(|elem| Cell::from(Text::from(elem)))
         .collect()
     }
 }
 
+/// Widths of the columns of track rows
+pub fn track_widths() -> Vec<Constraint> {
+    [
+        Constraint::Min(5),
+        Constraint::Length(6),
+        Constraint::Length(8),
+    ]
+    .into_iter()
+    .chain(stats::widths())
+    .collect()
+}
+
+/// Header of track rows titled by the first column
+pub fn track_header(title: &'static str) -> Row<'static> {
+    Row::new(
+        [title, "Artist", "Duration"]
+            .into_iter()
+            .map(Cell::new)
+            .chain(stats::header()),
+    )
+}
+
 impl Item for Track {}
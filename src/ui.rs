@@ -1,12 +1,14 @@
 use chrono::Local;
+use hhmmss::Hhmmss;
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Rect},
+    style::Stylize,
     text::Line,
     widgets::{Block, BorderType, Widget},
 };
 
-use crate::{app::App, menus::Menu};
+use crate::{app::App, library::LIBRARY, menus::Menu};
 
 impl Widget for &mut App {
     fn render(self, area: Rect, buf: &mut Buffer) {
@@ -16,10 +18,63 @@ impl Widget for &mut App {
                 Line::from(Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false))
                     .left_aligned(),
             )
+            .title(
+                Line::from(match self.state.sleep.as_ref() {
+                    Some(sleep) => match sleep.remaining(&self.state.player) {
+                        Some(remaining) => format!("Sleep {}", remaining.hhmmss()),
+                        None => String::from("Sleep at End of Track"),
+                    },
+                    None => String::new(),
+                })
+                .right_aligned(),
+            )
+            .title(
+                Line::from(if self.state.player.is_muted() {
+                    String::from("Muted")
+                } else {
+                    format!("Vol {:.0}%", self.state.player.get_volume() * 100.0)
+                })
+                .right_aligned(),
+            )
+            .title_bottom(
+                Line::from(if LIBRARY.is_scanning() {
+                    format!("Scanning library, {} tracks", LIBRARY.tracks().len())
+                } else {
+                    String::new()
+                })
+                .left_aligned(),
+            )
+            .title_bottom(
+                Line::from(
+                    self.state
+                        .player
+                        .get_warning()
+                        .unwrap_or_default()
+                        .to_string(),
+                )
+                .yellow()
+                .centered(),
+            )
+            .title_bottom(
+                Line::from(
+                    self.state
+                        .status
+                        .as_ref()
+                        .map(|(status, _)| status.clone())
+                        .unwrap_or_default(),
+                )
+                .right_aligned(),
+            )
             .title_alignment(Alignment::Center)
             .border_type(BorderType::Plain);
 
         self.menu.render(block.inner(area), buf, true);
+        if let Some(search) = self.search.as_mut() {
+            search.render(block.inner(area), buf);
+        }
+        if let Some(prompt) = self.prompt.as_ref() {
+            prompt.render(block.inner(area), buf);
+        }
         block.render(area, buf);
     }
 }
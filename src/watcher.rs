@@ -0,0 +1,195 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use futures::StreamExt;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+use tokio::sync::mpsc;
+
+use crate::{
+    CONFIG,
+    event::{Event, FsEvent},
+    library::{self, LIBRARY},
+    playlist::Playlist,
+    playlist_formats::is_playlist,
+    trace_dbg,
+    track::Track,
+};
+
+/// Changed files are read once nothing changed for this long, so copies finish first
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// Watches the music dir for playlists and the library dirs for tracks
+pub async fn run(sender: mpsc::UnboundedSender<Event>) {
+    if let Err(err) = watch(sender).await {
+        trace_dbg!(&err);
+    }
+}
+
+/// Loads the playlists in the music dir off the UI thread, sending each as changed
+pub async fn load_playlists(sender: mpsc::UnboundedSender<Event>) {
+    let _ = tokio::task::spawn_blocking(move || {
+        for playlist in CONFIG.load_playlists() {
+            let _ = sender.send(Event::Fs(FsEvent::PlaylistChanged(playlist)));
+        }
+    })
+    .await;
+}
+
+async fn watch(sender: mpsc::UnboundedSender<Event>) -> color_eyre::Result<()> {
+    let mut stream = Inotify::init()?.into_event_stream([0u8; 4096])?;
+    let mut watches = stream.watches();
+    let mut dirs = HashMap::new();
+    let library_dirs = CONFIG.library_dirs();
+    add_watch(&mut watches, &mut dirs, Path::new(&CONFIG.music_dir), false);
+    for dir in &library_dirs {
+        add_watch(&mut watches, &mut dirs, dir, true);
+    }
+
+    let mut changed = BTreeSet::new();
+    let mut removed = BTreeSet::new();
+    loop {
+        let event = if changed.is_empty() && removed.is_empty() {
+            stream.next().await
+        } else {
+            match tokio::time::timeout(SETTLE, stream.next()).await {
+                Ok(event) => event,
+                Err(_) => {
+                    let changed = std::mem::take(&mut changed);
+                    let removed = std::mem::take(&mut removed);
+                    let sender = sender.clone();
+                    tokio::task::spawn_blocking(move || flush(changed, removed, &sender)).await?;
+                    continue;
+                }
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
+        let event = event?;
+        if event.mask.contains(EventMask::IGNORED) {
+            dirs.remove(&event.wd);
+            continue;
+        }
+        let (Some(dir), Some(name)) = (dirs.get(&event.wd), event.name) else {
+            continue;
+        };
+        let path = dir.join(name);
+        let added = event
+            .mask
+            .intersects(EventMask::CLOSE_WRITE | EventMask::MOVED_TO);
+        let deleted = event
+            .mask
+            .intersects(EventMask::DELETE | EventMask::MOVED_FROM);
+        if event.mask.contains(EventMask::ISDIR) {
+            if event
+                .mask
+                .intersects(EventMask::CREATE | EventMask::MOVED_TO)
+                && library_dirs.iter().any(|dir| path.starts_with(dir))
+            {
+                add_watch(&mut watches, &mut dirs, &path, true);
+                let mut files = vec![];
+                library::walk(&path, &mut files);
+                changed.extend(files);
+            } else if deleted {
+                changed.retain(|changed: &PathBuf| !changed.starts_with(&path));
+                removed.insert(path);
+            }
+        } else if added {
+            removed.remove(&path);
+            changed.insert(path);
+        } else if deleted {
+            changed.remove(&path);
+            removed.insert(path);
+        }
+    }
+    Ok(())
+}
+
+/// Watches a dir and with `recursive` every dir under it
+fn add_watch(
+    watches: &mut Watches,
+    dirs: &mut HashMap<WatchDescriptor, PathBuf>,
+    dir: &Path,
+    recursive: bool,
+) {
+    let mask = WatchMask::CLOSE_WRITE
+        | WatchMask::CREATE
+        | WatchMask::DELETE
+        | WatchMask::MOVED_FROM
+        | WatchMask::MOVED_TO;
+    match watches.add(dir, mask) {
+        Ok(wd) => {
+            dirs.insert(wd, dir.to_path_buf());
+        }
+        Err(err) => {
+            trace_dbg!(err);
+            return;
+        }
+    }
+    if !recursive {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            add_watch(watches, dirs, &entry.path(), true);
+        }
+    }
+}
+
+/// Reads the settled changes into the library and tells the app about them
+fn flush(
+    changed: BTreeSet<PathBuf>,
+    removed: BTreeSet<PathBuf>,
+    sender: &mpsc::UnboundedSender<Event>,
+) {
+    let music_dir = Path::new(&CONFIG.music_dir);
+    let library_dirs = CONFIG.library_dirs();
+    let in_library = |path: &Path| library_dirs.iter().any(|dir| path.starts_with(dir));
+    let in_music_dir = |path: &Path| path.parent() == Some(music_dir);
+
+    let tracks = changed
+        .iter()
+        .filter(|path| library::is_audio(path) && in_library(path))
+        .filter_map(|path| match Track::try_from(path.clone()) {
+            Ok(track) => Some(track),
+            Err(err) => {
+                trace_dbg!(err);
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    let removed_tracks = removed
+        .iter()
+        .filter(|path| in_library(path))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !tracks.is_empty() || !removed_tracks.is_empty() {
+        LIBRARY.apply(&tracks, &removed_tracks);
+    }
+
+    let mut events = vec![];
+    events.extend(removed_tracks.into_iter().map(FsEvent::TrackRemoved));
+    events.extend(tracks.into_iter().map(FsEvent::TrackChanged));
+    events.extend(
+        removed
+            .into_iter()
+            .filter(|path| in_music_dir(path) && is_playlist(path))
+            .map(FsEvent::PlaylistRemoved),
+    );
+    events.extend(
+        changed
+            .into_iter()
+            .filter(|path| in_music_dir(path) && is_playlist(path))
+            .filter_map(|path| Playlist::try_from(path).ok())
+            .map(FsEvent::PlaylistChanged),
+    );
+    for event in events {
+        let _ = sender.send(Event::Fs(event));
+    }
+}